@@ -0,0 +1,897 @@
+use super::{
+    http::{request_error, truncate},
+    ActionResult,
+};
+use crate::app::{
+    config::{
+        rules::{
+            BitbucketAction, BitbucketInsights, BuildState, InsightsAnnotation,
+            InsightsAnnotations, PullRequestOperation,
+        },
+        webhook::BitbucketApi,
+    },
+    template, Error, FailureKind,
+};
+use reqwest::{Client, Method, RequestBuilder};
+use serde_json::{json, Value};
+use std::{collections::HashMap, env, time::Duration};
+use tera::Context;
+use tracing::{debug, info};
+
+const TIMEOUT_SECS: u64 = 30;
+const MAX_BODY_LEN: usize = 1024;
+const PAGE_LIMIT: u64 = 100;
+/// Build status descriptions are limited to 255 characters, truncating adds "..."
+const MAX_DESCRIPTION_LEN: usize = 252;
+/// Annotations sent per request, Bitbucket accepts up to 1000 per report
+const ANNOTATION_BATCH: usize = 100;
+
+/// Client of the Bitbucket API of the webhook the event came from, scoped to
+/// the webhook's repository
+pub(super) struct BitbucketClient {
+    client: Client,
+    repo_url: String,
+    token: String,
+}
+
+impl BitbucketClient {
+    /// Client of the webhook in the context, see `template::insert_webhook_context`
+    pub(super) fn from_context(context: &Context) -> Result<Self, Error> {
+        let api = context
+            .get("webhook")
+            .map(|webhook| webhook["bitbucket"]["api"].clone())
+            .filter(|api| !api.is_null())
+            .ok_or_else(|| {
+                Error::Action("Bitbucket action requires a Bitbucket webhook".to_string())
+            })?;
+        let api: BitbucketApi = serde_json::from_value(api)
+            .map_err(|e| Error::Action(format!("Invalid Bitbucket API configuration: {}", e)))?;
+
+        let token = env::var(&api.auth.token_from_env).map_err(|_| {
+            Error::Action(format!(
+                "Bitbucket token env var {} is not set",
+                api.auth.token_from_env
+            ))
+        })?;
+        let client = Client::builder()
+            .timeout(Duration::from_secs(TIMEOUT_SECS))
+            .build()
+            .map_err(|e| Error::Action(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            repo_url: format!(
+                "{}/api/latest/projects/{}/repos/{}",
+                api.base_url.trim_end_matches('/'),
+                api.project,
+                api.repo
+            ),
+            token,
+        })
+    }
+
+    /// Request a path of the repository's API
+    pub(super) fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}/{}", self.repo_url, path))
+            .bearer_auth(&self.token)
+    }
+
+    /// Send a request and return its status and JSON body, failing on a non 2xx status
+    pub(super) async fn send(
+        &self,
+        operation: &str,
+        request: RequestBuilder,
+    ) -> Result<(u16, Value), Error> {
+        let response = request.send().await.map_err(request_error)?;
+        let status = response.status().as_u16();
+        let body = response.text().await.map_err(request_error)?;
+        debug!("Bitbucket {} status: {}", operation, status);
+
+        if !(200..300).contains(&status) {
+            return Err(Error::ActionFailed(
+                FailureKind::Status(status),
+                format!(
+                    "Bitbucket {} returned unexpected status {}: {}",
+                    operation,
+                    status,
+                    truncate(body, MAX_BODY_LEN)
+                ),
+            ));
+        }
+
+        Ok((status, serde_json::from_str(&body).unwrap_or_default()))
+    }
+}
+
+/// Render and run a Bitbucket action on the event's pull request
+pub async fn exec_bitbucket_action(
+    action: &BitbucketAction,
+    context: &Context,
+    dry_run: bool,
+) -> Result<ActionResult, Error> {
+    let pull_request = context
+        .get("event")
+        .and_then(|event| event["pull_request"]["id"].as_u64())
+        .filter(|id| *id > 0)
+        .ok_or_else(|| {
+            Error::Action("Bitbucket action requires a pull request event".to_string())
+        })?;
+    let client = BitbucketClient::from_context(context)?;
+    let pull_request_path = format!("pull-requests/{}", pull_request);
+
+    // render everything before calling the API, so that a template error changes nothing
+    let comment = action
+        .comment
+        .as_ref()
+        .map(|comment| {
+            let text = render(&comment.text, context, "comment text")?;
+            let key = comment
+                .key
+                .as_ref()
+                .map(|key| render(key, context, "comment key"))
+                .transpose()?;
+            Ok::<_, Error>((text, key))
+        })
+        .transpose()?;
+    let build_status = action
+        .build_status
+        .as_ref()
+        .map(|build_status| {
+            let commit = render_commit(build_status.commit.as_deref(), context, "build status")?;
+            let mut payload = json!({
+                "state": build_status.state,
+                "key": render(&build_status.key, context, "build status key")?,
+                "url": render(&build_status.url, context, "build status url")?,
+            });
+            if let Some(name) = &build_status.name {
+                payload["name"] = json!(render(name, context, "build status name")?);
+            }
+            if let Some(description) = &build_status.description {
+                let description = render(description, context, "build status description")?;
+                payload["description"] = json!(truncate(description, MAX_DESCRIPTION_LEN));
+            }
+            Ok::<_, Error>((commit, payload))
+        })
+        .transpose()?;
+    let insights = action
+        .insights
+        .as_ref()
+        .map(|insights| render_insights(insights, context))
+        .transpose()?;
+    let reviewers = action
+        .reviewers
+        .iter()
+        .flatten()
+        .map(|reviewer| render(reviewer, context, "reviewer"))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if dry_run {
+        info!(
+            "Dry run Bitbucket action on pull request {}: comment={:?} build_status={:?} insights={:?} reviewers={:?} pull_request={:?}",
+            pull_request, comment, build_status, insights, reviewers, action.pull_request
+        );
+        // outputs are empty so that later actions still render
+        let outputs = comment
+            .as_ref()
+            .map(|_| HashMap::from([("comment_id".to_string(), String::new())]));
+        return Ok(ActionResult {
+            outputs,
+            ..ActionResult::dry_run("bitbucket")
+        });
+    }
+
+    let mut status = None;
+    let mut outputs = None;
+
+    if let Some((text, key)) = comment {
+        let (comment_status, comment_id) = match key {
+            Some(key) => upsert_comment(&client, &pull_request_path, &text, &key).await?,
+            None => post_comment(&client, &pull_request_path, &text).await?,
+        };
+        status = Some(comment_status);
+        outputs = Some(HashMap::from([("comment_id".to_string(), comment_id)]));
+    }
+
+    if let Some((commit, payload)) = build_status {
+        let request = client
+            .request(Method::POST, &format!("commits/{}/builds", commit))
+            .json(&payload);
+        status = Some(client.send("build status", request).await?.0);
+    }
+
+    if let Some(insights) = insights {
+        status = Some(put_insights(&client, insights).await?);
+    }
+
+    for reviewer in reviewers {
+        let request = client
+            .request(Method::POST, &format!("{}/participants", pull_request_path))
+            .json(&json!({"user": {"name": reviewer}, "role": "REVIEWER"}));
+        status = Some(client.send("add reviewer", request).await?.0);
+    }
+
+    if let Some(operation) = action.pull_request {
+        status = Some(change_pull_request(&client, &pull_request_path, operation).await?);
+    }
+
+    Ok(ActionResult {
+        action: "bitbucket".to_string(),
+        status,
+        outputs,
+        ..Default::default()
+    })
+}
+
+/// Post the build status of a rule run on the pull request's latest commit,
+/// keyed by the rule name and linking to the pull request. A rule run for a
+/// `foreach` item has its own status
+pub async fn report_rule_status(
+    rule_name: &str,
+    context: &Context,
+    state: BuildState,
+    error: Option<&str>,
+) -> Result<(), Error> {
+    let client = BitbucketClient::from_context(context)?;
+    let commit = render_commit(None, context, "rule status")?;
+    let link = context
+        .get("event")
+        .and_then(|event| event["pull_request"]["link"].as_str())
+        .unwrap_or_default();
+
+    let item = context.get("item").map(|item| match item {
+        Value::String(item) => item.clone(),
+        other => other.to_string(),
+    });
+    let (key, name) = match item {
+        Some(item) => (
+            format!("{}:{}", rule_name, item),
+            format!("{} {}", rule_name, item),
+        ),
+        None => (rule_name.to_string(), rule_name.to_string()),
+    };
+    let description = match (state, error) {
+        (BuildState::Inprogress, _) => "Running".to_string(),
+        (BuildState::Successful, _) => "Succeeded".to_string(),
+        (BuildState::Failed, error) => format!("Failed: {}", error.unwrap_or_default()),
+    };
+
+    let request = client
+        .request(Method::POST, &format!("commits/{}/builds", commit))
+        .json(&json!({
+            "state": state,
+            "key": key,
+            "name": name,
+            "url": link,
+            "description": truncate(description, MAX_DESCRIPTION_LEN),
+        }));
+    client.send("rule status", request).await?;
+
+    Ok(())
+}
+
+/// Post a comment and return the status and the comment's id
+async fn post_comment(
+    client: &BitbucketClient,
+    pull_request_path: &str,
+    text: &str,
+) -> Result<(u16, String), Error> {
+    let request = client
+        .request(Method::POST, &format!("{}/comments", pull_request_path))
+        .json(&json!({ "text": text }));
+    let (status, comment) = client.send("comment", request).await?;
+
+    Ok((status, field(&comment, "id", "comment")?.to_string()))
+}
+
+/// Update the comment carrying the key, or post it if there is none. The key is
+/// kept in the comment as a markdown comment, which Bitbucket does not display
+async fn upsert_comment(
+    client: &BitbucketClient,
+    pull_request_path: &str,
+    text: &str,
+    key: &str,
+) -> Result<(u16, String), Error> {
+    let marker = format!("[//]: # (git-actions:{})", key);
+    let text = format!("{}\n\n{}", text, marker);
+
+    let Some(existing) = find_comment(client, pull_request_path, &marker).await? else {
+        return post_comment(client, pull_request_path, &text).await;
+    };
+    let id = field(&existing, "id", "comment")?;
+    let version = field(&existing, "version", "comment")?;
+    let request = client
+        .request(
+            Method::PUT,
+            &format!("{}/comments/{}", pull_request_path, id),
+        )
+        .json(&json!({ "text": text, "version": version }));
+    let (status, comment) = client.send("update comment", request).await?;
+
+    Ok((
+        status,
+        field(&comment, "id", "updated comment")?.to_string(),
+    ))
+}
+
+/// Find the comment containing the marker in the pull request's activities
+async fn find_comment(
+    client: &BitbucketClient,
+    pull_request_path: &str,
+    marker: &str,
+) -> Result<Option<Value>, Error> {
+    let mut start = 0;
+    loop {
+        let request = client
+            .request(Method::GET, &format!("{}/activities", pull_request_path))
+            .query(&[("start", start), ("limit", PAGE_LIMIT)]);
+        let (_, page) = client.send("list comments", request).await?;
+
+        let comment = page["values"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|activity| activity["action"] == "COMMENTED")
+            .map(|activity| &activity["comment"])
+            .find(|comment| {
+                comment["text"]
+                    .as_str()
+                    .is_some_and(|text| text.contains(marker))
+            });
+        if let Some(comment) = comment {
+            return Ok(Some(comment.clone()));
+        }
+
+        match page["nextPageStart"].as_u64() {
+            Some(next) if page["isLastPage"] == false => start = next,
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// Approve, decline or merge the pull request. Declining and merging need the
+/// pull request's current version
+async fn change_pull_request(
+    client: &BitbucketClient,
+    pull_request_path: &str,
+    operation: PullRequestOperation,
+) -> Result<u16, Error> {
+    let path = match operation {
+        PullRequestOperation::Approve => {
+            let request = client.request(Method::POST, &format!("{}/approve", pull_request_path));
+            return Ok(client.send("approve", request).await?.0);
+        }
+        PullRequestOperation::Decline => "decline",
+        PullRequestOperation::Merge => "merge",
+    };
+
+    let (_, current) = client
+        .send(
+            "get pull request",
+            client.request(Method::GET, pull_request_path),
+        )
+        .await?;
+    let version = field(&current, "version", "pull request")?;
+    let request = client
+        .request(Method::POST, &format!("{}/{}", pull_request_path, path))
+        .query(&[("version", version)]);
+
+    Ok(client.send(path, request).await?.0)
+}
+
+/// Read a numeric field of an API response, failing when the response does not
+/// have it rather than sending a request with a placeholder
+fn field(value: &Value, name: &str, what: &str) -> Result<i64, Error> {
+    value[name]
+        .as_i64()
+        .ok_or_else(|| Error::Action(format!("Bitbucket {} has no {}", what, name)))
+}
+
+/// Rendered Code Insights report of an action
+#[derive(Debug)]
+struct Insights {
+    commit: String,
+    key: String,
+    report: Value,
+    annotations: Option<Vec<InsightsAnnotation>>,
+}
+
+/// Render a Code Insights report and its annotations
+fn render_insights(insights: &BitbucketInsights, context: &Context) -> Result<Insights, Error> {
+    let commit = render_commit(insights.commit.as_deref(), context, "insights report")?;
+    let key = render(&insights.key, context, "insights key")?;
+
+    let mut report = json!({ "title": render(&insights.title, context, "insights title")? });
+    if let Some(result) = &insights.result {
+        let result = render(result, context, "insights result")?
+            .trim()
+            .to_uppercase();
+        if result != "PASS" && result != "FAIL" {
+            return Err(Error::Action(format!(
+                "Insights result must be PASS or FAIL: {}",
+                result
+            )));
+        }
+        report["result"] = json!(result);
+    }
+    for (field, value) in [
+        ("details", &insights.details),
+        ("reporter", &insights.reporter),
+        ("link", &insights.link),
+    ] {
+        if let Some(value) = value {
+            report[field] = json!(render(value, context, &format!("insights {}", field))?);
+        }
+    }
+    if let Some(data) = &insights.data {
+        report["data"] = template::render_template_value(&json!(data), context)
+            .map_err(|e| Error::Action(format!("Failed to render insights data: {}", e)))?;
+    }
+
+    // both forms are checked against the annotation fields
+    let annotations = match &insights.annotations {
+        None => None,
+        Some(InsightsAnnotations::List(annotations)) => {
+            let rendered =
+                template::render_template_value(&json!(annotations), context).map_err(|e| {
+                    Error::Action(format!("Failed to render insights annotations: {}", e))
+                })?;
+            Some(
+                serde_json::from_value(rendered)
+                    .map_err(|e| Error::Action(format!("Invalid insights annotations: {}", e)))?,
+            )
+        }
+        Some(InsightsAnnotations::Template(template_str)) => {
+            let rendered = render(template_str, context, "insights annotations")?;
+            Some(
+                serde_json::from_str(&rendered)
+                    .map_err(|e| Error::Action(format!("Invalid insights annotations: {}", e)))?,
+            )
+        }
+    };
+
+    Ok(Insights {
+        commit,
+        key,
+        report,
+        annotations,
+    })
+}
+
+/// Create or replace a Code Insights report, then replace its annotations
+async fn put_insights(client: &BitbucketClient, insights: Insights) -> Result<u16, Error> {
+    let report_path = format!("commits/{}/reports/{}", insights.commit, insights.key);
+    let request = client
+        .request(Method::PUT, &report_path)
+        .json(&insights.report);
+    let (mut status, _) = client.send("insights report", request).await?;
+
+    let Some(annotations) = insights.annotations else {
+        return Ok(status);
+    };
+    let annotations_path = format!("{}/annotations", report_path);
+    client
+        .send(
+            "delete insights annotations",
+            client.request(Method::DELETE, &annotations_path),
+        )
+        .await?;
+    for batch in annotations.chunks(ANNOTATION_BATCH) {
+        let request = client
+            .request(Method::POST, &annotations_path)
+            .json(&json!({ "annotations": batch }));
+        status = client.send("insights annotations", request).await?.0;
+    }
+
+    Ok(status)
+}
+
+/// Render the commit of an operation, the pull request's latest commit by default
+fn render_commit(
+    commit: Option<&str>,
+    context: &Context,
+    operation: &str,
+) -> Result<String, Error> {
+    let commit = match commit {
+        Some(commit) => render(commit, context, &format!("{} commit", operation))?,
+        None => context
+            .get("event")
+            .and_then(|event| event["pull_request"]["commit"].as_str())
+            .unwrap_or_default()
+            .to_string(),
+    };
+    if commit.is_empty() {
+        return Err(Error::Action(format!(
+            "Bitbucket {} requires a commit",
+            operation
+        )));
+    }
+
+    Ok(commit)
+}
+
+/// Render a template of the action
+fn render(template_str: &str, context: &Context, field: &str) -> Result<String, Error> {
+    template::render_template(template_str, context)
+        .map_err(|e| Error::Action(format!("Failed to render {}: {}", field, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::rules::{
+        BitbucketBuildStatus, BitbucketComment, BuildState, InsightsData,
+    };
+    use wiremock::matchers::{body_json, body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const REPO_PATH: &str = "/api/latest/projects/PRJ/repos/repo";
+
+    fn create_test_context(base_url: &str) -> Context {
+        env::set_var("GIT_ACTIONS_TEST_BITBUCKET_TOKEN", "bitbucket-token");
+        let mut context = Context::new();
+        context.insert(
+            "event",
+            &json!({"branch": "feature/x", "pull_request": {"id": 7, "commit": "abc123"}}),
+        );
+        context.insert(
+            "webhook",
+            &json!({
+                "name": "bitbucket",
+                "bitbucket": {
+                    "api": {
+                        "baseUrl": base_url,
+                        "project": "PRJ",
+                        "repo": "repo",
+                        "auth": {"type": "token", "tokenFromEnv": "GIT_ACTIONS_TEST_BITBUCKET_TOKEN"}
+                    }
+                }
+            }),
+        );
+        context
+    }
+
+    #[tokio::test]
+    async fn test_exec_bitbucket_action_runs_operations() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(format!("{}/pull-requests/7/comments", REPO_PATH)))
+            .and(header("Authorization", "Bearer bitbucket-token"))
+            .and(body_json(json!({"text": "Deployed feature/x"})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({"id": 11})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{}/commits/abc123/builds", REPO_PATH)))
+            .and(body_json(json!({
+                "state": "SUCCESSFUL",
+                "key": "deploy",
+                "url": "https://ci.example.com/feature/x",
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{}/pull-requests/7/participants", REPO_PATH)))
+            .and(body_partial_json(json!({"user": {"name": "alice"}})))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("{}/pull-requests/7", REPO_PATH)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"version": 3})))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{}/pull-requests/7/merge", REPO_PATH)))
+            .and(query_param("version", "3"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let action = BitbucketAction {
+            comment: Some(BitbucketComment {
+                text: "Deployed {{ event.branch }}".to_string(),
+                key: None,
+            }),
+            build_status: Some(BitbucketBuildStatus {
+                state: BuildState::Successful,
+                key: "deploy".to_string(),
+                url: "https://ci.example.com/{{ event.branch }}".to_string(),
+                ..Default::default()
+            }),
+            reviewers: Some(vec!["alice".to_string()]),
+            pull_request: Some(PullRequestOperation::Merge),
+            ..Default::default()
+        };
+
+        // Execute
+        let result =
+            exec_bitbucket_action(&action, &create_test_context(&mock_server.uri()), false)
+                .await
+                .unwrap();
+
+        // Verify
+        assert_eq!(result.action, "bitbucket");
+        assert_eq!(result.status, Some(200));
+        assert_eq!(result.outputs.unwrap()["comment_id"], "11");
+    }
+
+    #[tokio::test]
+    async fn test_exec_bitbucket_action_updates_keyed_comment() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("{}/pull-requests/7/activities", REPO_PATH)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "isLastPage": true,
+                "values": [
+                    {"action": "APPROVED"},
+                    {"action": "COMMENTED", "comment": {"id": 5, "version": 2, "text": "other"}},
+                    {"action": "COMMENTED", "comment": {
+                        "id": 9,
+                        "version": 4,
+                        "text": "old\n\n[//]: # (git-actions:summary)"
+                    }},
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path(format!("{}/pull-requests/7/comments/9", REPO_PATH)))
+            .and(body_json(json!({
+                "text": "new\n\n[//]: # (git-actions:summary)",
+                "version": 4,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": 9})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let action = BitbucketAction {
+            comment: Some(BitbucketComment {
+                text: "new".to_string(),
+                key: Some("summary".to_string()),
+            }),
+            ..Default::default()
+        };
+
+        // Execute
+        let result =
+            exec_bitbucket_action(&action, &create_test_context(&mock_server.uri()), false)
+                .await
+                .unwrap();
+
+        // Verify
+        assert_eq!(result.outputs.unwrap()["comment_id"], "9");
+    }
+
+    #[tokio::test]
+    async fn test_exec_bitbucket_action_reports_insights() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        let report_path = format!("{}/commits/abc123/reports/lint", REPO_PATH);
+        Mock::given(method("PUT"))
+            .and(path(report_path.clone()))
+            .and(body_json(json!({
+                "title": "Lint",
+                "result": "FAIL",
+                "data": [{"title": "Issues", "type": "NUMBER", "value": 1}],
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path(format!("{}/annotations", report_path)))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{}/annotations", report_path)))
+            .and(body_json(json!({"annotations": [{
+                "message": "unused variable",
+                "severity": "MEDIUM",
+                "path": "src/main.rs",
+                "line": 3,
+                "type": "CODE_SMELL",
+            }]})))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut context = create_test_context(&mock_server.uri());
+        template::insert_step_context(
+            &mut context,
+            "lint",
+            &HashMap::from([(
+                "annotations".to_string(),
+                r#"[{"path": "src/main.rs", "line": 3, "message": "unused variable", "severity": "MEDIUM", "type": "CODE_SMELL"}]"#
+                    .to_string(),
+            )]),
+        );
+        let action = BitbucketAction {
+            insights: Some(BitbucketInsights {
+                key: "lint".to_string(),
+                title: "Lint".to_string(),
+                result: Some(
+                    "{% if steps.lint.outputs.annotations == '[]' %}pass{% else %}fail{% endif %}"
+                        .to_string(),
+                ),
+                data: Some(vec![InsightsData {
+                    title: "Issues".to_string(),
+                    data_type: Some("NUMBER".to_string()),
+                    value: json!(1),
+                }]),
+                annotations: Some(InsightsAnnotations::Template(
+                    "{{ steps.lint.outputs.annotations }}".to_string(),
+                )),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let invalid = BitbucketAction {
+            insights: Some(BitbucketInsights {
+                key: "lint".to_string(),
+                title: "Lint".to_string(),
+                annotations: Some(InsightsAnnotations::Template(
+                    r#"[{"message": "no severity"}]"#.to_string(),
+                )),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_bitbucket_action(&action, &context, false).await;
+        let invalid = exec_bitbucket_action(&invalid, &context, false).await;
+
+        // Verify
+        assert_eq!(result.unwrap().status, Some(204));
+        assert!(matches!(invalid, Err(Error::Action(_))));
+    }
+
+    #[tokio::test]
+    async fn test_exec_bitbucket_action_requires_response_fields() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("{}/pull-requests/7", REPO_PATH)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{}/pull-requests/7/merge", REPO_PATH)))
+            .respond_with(ResponseTemplate::new(409))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{}/pull-requests/7/comments", REPO_PATH)))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({})))
+            .mount(&mock_server)
+            .await;
+        let merge = BitbucketAction {
+            pull_request: Some(PullRequestOperation::Merge),
+            ..Default::default()
+        };
+        let comment = BitbucketAction {
+            comment: Some(BitbucketComment {
+                text: "Deployed".to_string(),
+                key: None,
+            }),
+            ..Default::default()
+        };
+        let context = create_test_context(&mock_server.uri());
+
+        // Execute
+        let merged = exec_bitbucket_action(&merge, &context, false).await;
+        let commented = exec_bitbucket_action(&comment, &context, false).await;
+
+        // Verify
+        match merged {
+            Err(Error::Action(message)) => assert!(message.contains("version"), "{}", message),
+            other => panic!("Expected action error, got {:?}", other),
+        }
+        match commented {
+            Err(Error::Action(message)) => assert!(message.contains("id"), "{}", message),
+            other => panic!("Expected action error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_bitbucket_action_truncates_build_status_description() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        let description = format!("{}...", "x".repeat(MAX_DESCRIPTION_LEN));
+        Mock::given(method("POST"))
+            .and(path(format!("{}/commits/abc123/builds", REPO_PATH)))
+            .and(body_partial_json(json!({ "description": description })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let action = BitbucketAction {
+            build_status: Some(BitbucketBuildStatus {
+                state: BuildState::Failed,
+                key: "deploy".to_string(),
+                url: "https://ci.example.com".to_string(),
+                description: Some("x".repeat(300)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Execute
+        let result =
+            exec_bitbucket_action(&action, &create_test_context(&mock_server.uri()), false).await;
+
+        // Verify
+        assert_eq!(result.unwrap().status, Some(204));
+    }
+
+    #[tokio::test]
+    async fn test_exec_bitbucket_action_failure_and_dry_run() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(409).set_body_string("conflict"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let action = BitbucketAction {
+            pull_request: Some(PullRequestOperation::Approve),
+            ..Default::default()
+        };
+        let context = create_test_context(&mock_server.uri());
+
+        // Execute
+        let dry_run = exec_bitbucket_action(&action, &context, true).await;
+        let failed = exec_bitbucket_action(&action, &context, false).await;
+        let no_webhook = exec_bitbucket_action(&action, &Context::new(), false).await;
+
+        // Verify
+        assert!(dry_run.unwrap().dry_run);
+        assert!(matches!(
+            failed,
+            Err(Error::ActionFailed(FailureKind::Status(409), _))
+        ));
+        assert!(matches!(no_webhook, Err(Error::Action(_))));
+    }
+
+    #[test]
+    fn test_bitbucket_client_requires_token() {
+        // Setup
+        let mut context = create_test_context("http://localhost");
+        context.insert(
+            "webhook",
+            &json!({"bitbucket": {"api": {
+                "baseUrl": "http://localhost",
+                "project": "PRJ",
+                "repo": "repo",
+                "auth": {"type": "token", "tokenFromEnv": "GIT_ACTIONS_TEST_UNSET_TOKEN"}
+            }}}),
+        );
+
+        // Execute
+        let result = BitbucketClient::from_context(&context);
+
+        // Verify
+        match result {
+            Err(Error::Action(message)) => {
+                assert!(
+                    message.contains("GIT_ACTIONS_TEST_UNSET_TOKEN"),
+                    "{}",
+                    message
+                )
+            }
+            _ => panic!("expected a missing token error"),
+        }
+    }
+}
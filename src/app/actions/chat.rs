@@ -0,0 +1,405 @@
+use super::{
+    http::{request_error, truncate},
+    ActionResult,
+};
+use crate::app::{
+    config::rules::{SlackAction, TeamsAction},
+    template, Error, FailureKind,
+};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tera::Context;
+use tracing::{debug, info};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const MAX_BODY_LEN: usize = 1024;
+
+/// Render and post a Slack message
+pub async fn exec_slack_action(
+    action: &SlackAction,
+    context: &Context,
+    dry_run: bool,
+) -> Result<ActionResult, Error> {
+    let summary = Summary::from_context(context);
+    let text = action
+        .text
+        .as_ref()
+        .map(|text| render(text, context, "Slack text"))
+        .transpose()?;
+    let blocks = action
+        .blocks
+        .as_ref()
+        .map(|blocks| {
+            template::render_template_value(blocks, context)
+                .map_err(|e| Error::Action(format!("Failed to render Slack blocks: {}", e)))
+        })
+        .transpose()?;
+
+    // the summary blocks are only added when neither text nor blocks are set
+    let payload = match (text, blocks) {
+        (Some(text), None) => json!({ "text": text }),
+        (text, Some(blocks)) => json!({
+            "text": text.unwrap_or_else(|| summary.text()),
+            "blocks": blocks,
+        }),
+        (None, None) => json!({
+            "text": summary.text(),
+            "blocks": summary.slack_blocks(),
+        }),
+    };
+
+    post(
+        "slack",
+        &action.webhook_url,
+        action.timeout,
+        &payload,
+        context,
+        dry_run,
+    )
+    .await
+}
+
+/// Render and post a Microsoft Teams message
+pub async fn exec_teams_action(
+    action: &TeamsAction,
+    context: &Context,
+    dry_run: bool,
+) -> Result<ActionResult, Error> {
+    let summary = Summary::from_context(context);
+    let card = match (&action.card, &action.text) {
+        (Some(card), _) => template::render_template_value(card, context)
+            .map_err(|e| Error::Action(format!("Failed to render Teams card: {}", e)))?,
+        (None, Some(text)) => summary.teams_card(vec![json!({
+            "type": "TextBlock",
+            "text": render(text, context, "Teams text")?,
+            "wrap": true,
+        })]),
+        (None, None) => summary.teams_card(summary.teams_body()),
+    };
+    let payload = json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": card,
+        }],
+    });
+
+    post(
+        "teams",
+        &action.webhook_url,
+        action.timeout,
+        &payload,
+        context,
+        dry_run,
+    )
+    .await
+}
+
+/// Post a message to an incoming webhook. The URL is a credential, it is left
+/// out of the errors
+async fn post(
+    action: &str,
+    webhook_url: &str,
+    timeout: Option<u64>,
+    payload: &Value,
+    context: &Context,
+    dry_run: bool,
+) -> Result<ActionResult, Error> {
+    let url = render(webhook_url, context, "webhook URL")?;
+    if dry_run {
+        info!("Dry run {} action: {}", action, payload);
+        return Ok(ActionResult::dry_run(action));
+    }
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(timeout.unwrap_or(DEFAULT_TIMEOUT_SECS)))
+        .build()
+        .map_err(|e| Error::Action(format!("Failed to create HTTP client: {}", e)))?;
+    let response = client
+        .post(&url)
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| request_error(e.without_url()))?;
+    let status = response.status().as_u16();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| request_error(e.without_url()))?;
+    debug!("{} action status: {}", action, status);
+
+    if !(200..300).contains(&status) {
+        return Err(Error::ActionFailed(
+            FailureKind::Status(status),
+            format!(
+                "{} webhook returned unexpected status {}: {}",
+                action,
+                status,
+                truncate(body, MAX_BODY_LEN)
+            ),
+        ));
+    }
+
+    Ok(ActionResult {
+        action: action.to_string(),
+        status: Some(status),
+        body: Some(truncate(body, MAX_BODY_LEN)),
+        ..Default::default()
+    })
+}
+
+/// Pull request details of the default messages
+struct Summary {
+    title: String,
+    author: String,
+    branch: String,
+    rule: String,
+    link: String,
+}
+
+impl Summary {
+    fn from_context(context: &Context) -> Self {
+        let event = context.get("event").cloned().unwrap_or_default();
+        let pull_request = &event["pull_request"];
+        let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+        let author = match text(&pull_request["author"]["display_name"]) {
+            name if name.is_empty() => text(&pull_request["author"]["username"]),
+            name => name,
+        };
+
+        Self {
+            title: text(&pull_request["title"]),
+            author,
+            branch: text(&event["branch"]),
+            rule: context
+                .get("rule")
+                .map(|rule| text(&rule["name"]))
+                .unwrap_or_default(),
+            link: text(&pull_request["link"]),
+        }
+    }
+
+    /// Plain text summary, used as the notification text
+    fn text(&self) -> String {
+        format!(
+            "Rule {} matched {} by {} on {}",
+            self.rule, self.title, self.author, self.branch
+        )
+    }
+
+    fn slack_blocks(&self) -> Value {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        };
+        let title = match self.link.as_str() {
+            "" => format!("*{}*", escape(&self.title)),
+            link => format!("*<{}|{}>*", link, escape(&self.title)),
+        };
+
+        json!([
+            {
+                "type": "section",
+                "text": {"type": "mrkdwn", "text": title},
+            },
+            {
+                "type": "context",
+                "elements": [
+                    {"type": "mrkdwn", "text": format!("Rule: *{}*", escape(&self.rule))},
+                    {"type": "mrkdwn", "text": format!("Author: {}", escape(&self.author))},
+                    {"type": "mrkdwn", "text": format!("Branch: `{}`", escape(&self.branch))},
+                ],
+            },
+        ])
+    }
+
+    fn teams_body(&self) -> Vec<Value> {
+        vec![
+            json!({
+                "type": "TextBlock",
+                "text": self.title,
+                "size": "Medium",
+                "weight": "Bolder",
+                "wrap": true,
+            }),
+            json!({
+                "type": "FactSet",
+                "facts": [
+                    {"title": "Rule", "value": self.rule},
+                    {"title": "Author", "value": self.author},
+                    {"title": "Branch", "value": self.branch},
+                ],
+            }),
+        ]
+    }
+
+    /// Adaptive Card with the body, linking to the pull request
+    fn teams_card(&self, body: Vec<Value>) -> Value {
+        let mut card = json!({
+            "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+            "type": "AdaptiveCard",
+            "version": "1.4",
+            "body": body,
+        });
+        if !self.link.is_empty() {
+            card["actions"] = json!([{
+                "type": "Action.OpenUrl",
+                "title": "Open pull request",
+                "url": self.link,
+            }]);
+        }
+        card
+    }
+}
+
+/// Render a template of the action
+fn render(template_str: &str, context: &Context, field: &str) -> Result<String, Error> {
+    template::render_template(template_str, context)
+        .map_err(|e| Error::Action(format!("Failed to render {}: {}", field, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn create_test_context() -> Context {
+        let mut context = Context::new();
+        context.insert(
+            "event",
+            &json!({
+                "branch": "feature/x",
+                "pull_request": {
+                    "title": "Add <feature>",
+                    "link": "https://bitbucket/pr/7",
+                    "author": {"username": "alice", "display_name": "Alice"},
+                },
+            }),
+        );
+        context.insert("rule", &json!({"name": "deploy"}));
+        context
+    }
+
+    #[tokio::test]
+    async fn test_exec_slack_action_defaults_to_summary() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/slack"))
+            .and(body_partial_json(json!({
+                "text": "Rule deploy matched Add <feature> by Alice on feature/x",
+                "blocks": [{
+                    "type": "section",
+                    "text": {"type": "mrkdwn", "text": "*<https://bitbucket/pr/7|Add &lt;feature&gt;>*"},
+                }],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let action = SlackAction {
+            webhook_url: format!("{}/slack", mock_server.uri()),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_slack_action(&action, &create_test_context(), false).await;
+
+        // Verify
+        let result = result.unwrap();
+        assert_eq!(result.action, "slack");
+        assert_eq!(result.body.as_deref(), Some("ok"));
+    }
+
+    #[tokio::test]
+    async fn test_exec_slack_action_renders_blocks() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_json(json!({
+                "text": "Deployed feature/x",
+                "blocks": [{"type": "section", "text": {"type": "plain_text", "text": "feature/x"}}],
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let action = SlackAction {
+            webhook_url: mock_server.uri(),
+            text: Some("Deployed {{ event.branch }}".to_string()),
+            blocks: Some(json!([
+                {"type": "section", "text": {"type": "plain_text", "text": "{{ event.branch }}"}}
+            ])),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_slack_action(&action, &create_test_context(), false).await;
+
+        // Verify
+        assert_eq!(result.unwrap().status, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_exec_teams_action_defaults_to_card() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/teams"))
+            .and(body_partial_json(json!({
+                "type": "message",
+                "attachments": [{
+                    "contentType": "application/vnd.microsoft.card.adaptive",
+                    "content": {
+                        "type": "AdaptiveCard",
+                        "actions": [{"type": "Action.OpenUrl", "url": "https://bitbucket/pr/7"}],
+                    },
+                }],
+            })))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let action = TeamsAction {
+            webhook_url: format!("{}/teams", mock_server.uri()),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_teams_action(&action, &create_test_context(), false).await;
+
+        // Verify
+        assert_eq!(result.unwrap().status, Some(202));
+    }
+
+    #[tokio::test]
+    async fn test_exec_teams_action_failure_and_dry_run() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("Bad payload"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let action = TeamsAction {
+            webhook_url: mock_server.uri(),
+            text: Some("Deployed {{ event.branch }}".to_string()),
+            ..Default::default()
+        };
+        let context = create_test_context();
+
+        // Execute
+        let dry_run = exec_teams_action(&action, &context, true).await;
+        let failed = exec_teams_action(&action, &context, false).await;
+
+        // Verify
+        assert!(dry_run.unwrap().dry_run);
+        assert!(matches!(
+            failed,
+            Err(Error::ActionFailed(FailureKind::Status(400), _))
+        ));
+    }
+}
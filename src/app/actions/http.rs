@@ -0,0 +1,856 @@
+use super::ActionResult;
+use crate::app::{
+    config::rules::{ExpectedStatus, HttpAction, HttpAuth, MultipartField, OutputExtractor},
+    template, Error, FailureKind,
+};
+use regex::Regex;
+use reqwest::{
+    header::HeaderMap,
+    multipart::{Form, Part},
+    redirect, Client, Method,
+};
+use serde_json::Value;
+use std::{collections::HashMap, time::Duration};
+use tera::Context;
+use tracing::{debug, info, warn};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+const MAX_BODY_LEN: usize = 1024;
+
+/// Render and send an HTTP action
+pub async fn exec_http_action(
+    action: &HttpAction,
+    context: &Context,
+    dry_run: bool,
+) -> Result<ActionResult, Error> {
+    // create a new HTTP client with the action's options
+    let http_client = build_client(action)?;
+
+    // Render templates in method and url
+    let method = template::render_template(&action.method, context)
+        .map_err(|e| Error::Action(format!("Failed to render method template: {}", e)))?;
+
+    let url = template::render_template(&action.url, context)
+        .map_err(|e| Error::Action(format!("Failed to render URL template: {}", e)))?;
+
+    // set request method
+    let method = match method.to_uppercase().as_str() {
+        "GET" => Method::GET,
+        "POST" => Method::POST,
+        "PUT" => Method::PUT,
+        "PATCH" => Method::PATCH,
+        "DELETE" => Method::DELETE,
+        "HEAD" => Method::HEAD,
+        _ => {
+            return Err(Error::Action(format!(
+                "Unsupported HTTP method: {}",
+                method
+            )))
+        }
+    };
+    let mut client = http_client.request(method, &url);
+
+    // query parameters with template rendering
+    if let Some(query) = &action.query {
+        let rendered_query = template::render_template_map(query, context);
+        client = client.query(&rendered_query);
+    }
+
+    // headers with template rendering
+    if let Some(headers) = &action.headers {
+        let rendered_headers = template::render_template_map(headers, context);
+        for (key, value) in rendered_headers.iter() {
+            client = client.header(key, value);
+        }
+    }
+
+    // authentication with template rendering
+    if let Some(auth) = &action.auth {
+        client = match auth {
+            HttpAuth::Basic { basic } => {
+                let username = template::render_template(&basic.username, context)
+                    .map_err(|e| Error::Action(format!("Failed to render username: {}", e)))?;
+                let password = basic
+                    .password
+                    .as_ref()
+                    .map(|password| template::render_template(password, context))
+                    .transpose()
+                    .map_err(|e| Error::Action(format!("Failed to render password: {}", e)))?;
+                client.basic_auth(username, password)
+            }
+            HttpAuth::Bearer { bearer } => {
+                let token = template::render_template(bearer, context)
+                    .map_err(|e| Error::Action(format!("Failed to render bearer token: {}", e)))?;
+                client.bearer_auth(token)
+            }
+        };
+    }
+
+    // body with template rendering
+    let bodies = [
+        action.body.is_some(),
+        action.json.is_some(),
+        action.form.is_some(),
+        action.multipart.is_some(),
+    ];
+    if bodies.iter().filter(|body| **body).count() > 1 {
+        return Err(Error::Action(
+            "Only one of body, json, form or multipart can be set".to_string(),
+        ));
+    }
+
+    if let Some(body) = &action.body {
+        let rendered_body = template::render_template(body, context)
+            .map_err(|e| Error::Action(format!("Failed to render body template: {}", e)));
+
+        client = client.body(rendered_body?);
+    }
+
+    if let Some(json) = &action.json {
+        let rendered_json = template::render_template_value(json, context)
+            .map_err(|e| Error::Action(format!("Failed to render json template: {}", e)))?;
+
+        client = client.json(&rendered_json);
+    }
+
+    if let Some(form) = &action.form {
+        let rendered_form = render_fields(form, context)?;
+
+        client = client.form(&rendered_form);
+    }
+
+    if let Some(multipart) = &action.multipart {
+        client = client.multipart(build_multipart(multipart, context).await?);
+    }
+
+    let request = client.build().map_err(|e| Error::Action(e.to_string()))?;
+
+    if dry_run {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default();
+        // header values may hold credentials, only their names are logged
+        let headers: Vec<&str> = request.headers().keys().map(|name| name.as_str()).collect();
+        info!(
+            "Dry run HTTP action: {} {} headers={:?} body={}",
+            request.method(),
+            request.url(),
+            headers,
+            body
+        );
+        // outputs are empty so that later actions still render
+        let outputs = action.outputs.as_ref().map(|outputs| {
+            outputs
+                .keys()
+                .map(|name| (name.clone(), String::new()))
+                .collect()
+        });
+        return Ok(ActionResult {
+            outputs,
+            ..ActionResult::dry_run("http")
+        });
+    }
+
+    // send the request
+    let target = format!("{} {}", request.method(), request.url());
+    let response = http_client.execute(request).await.map_err(request_error)?;
+
+    let status = response.status().as_u16();
+    debug!("Action status: {}", status);
+
+    let headers = response.headers().clone();
+    let body = response.text().await.map_err(request_error)?;
+
+    if !is_expected_status(action.expected_status.as_deref(), status)? {
+        return Err(Error::ActionFailed(
+            FailureKind::Status(status),
+            format!(
+                "HTTP action {} returned unexpected status {}: {}",
+                target,
+                status,
+                truncate(body, MAX_BODY_LEN)
+            ),
+        ));
+    }
+
+    let outputs = action
+        .outputs
+        .as_ref()
+        .map(|outputs| extract_outputs(outputs, &headers, &body))
+        .transpose()?;
+    debug!("Action outputs: {:?}", outputs);
+
+    Ok(ActionResult {
+        action: "http".to_string(),
+        status: Some(status),
+        body: Some(truncate(body, MAX_BODY_LEN)),
+        outputs,
+        ..Default::default()
+    })
+}
+
+/// Extract the configured outputs from a response
+fn extract_outputs(
+    outputs: &HashMap<String, OutputExtractor>,
+    headers: &HeaderMap,
+    body: &str,
+) -> Result<HashMap<String, String>, Error> {
+    outputs
+        .iter()
+        .map(|(name, extractor)| {
+            extract_output(extractor, headers, body)
+                .map(|value| (name.clone(), value))
+                .map_err(|e| Error::Action(format!("Failed to extract output {}: {}", name, e)))
+        })
+        .collect()
+}
+
+/// Extract a value from a response header or body with a JSON pointer and/or a regex
+fn extract_output(
+    extractor: &OutputExtractor,
+    headers: &HeaderMap,
+    body: &str,
+) -> Result<String, String> {
+    let mut value = match &extractor.header {
+        Some(header) => headers
+            .get(header)
+            .ok_or_else(|| format!("header {} not found", header))?
+            .to_str()
+            .map_err(|e| format!("header {} is not valid text: {}", header, e))?
+            .to_string(),
+        None => body.to_string(),
+    };
+
+    if let Some(pointer) = &extractor.json {
+        let json: Value =
+            serde_json::from_str(&value).map_err(|e| format!("invalid JSON: {}", e))?;
+        value = match json.pointer(pointer) {
+            Some(Value::String(text)) => text.clone(),
+            Some(other) => other.to_string(),
+            None => return Err(format!("JSON pointer {} not found", pointer)),
+        };
+    }
+
+    if let Some(regex) = &extractor.regex {
+        let re = Regex::new(regex).map_err(|e| format!("invalid regex {}: {}", regex, e))?;
+        let captures = re
+            .captures(&value)
+            .ok_or_else(|| format!("regex {} did not match", regex))?;
+        value = captures
+            .get(1)
+            .or_else(|| captures.get(0))
+            .map(|m| m.as_str().to_string())
+            .unwrap_or_default();
+    }
+
+    Ok(value)
+}
+
+/// Classify a failed request so that retry policies can match timeouts and connection errors
+pub(super) fn request_error(e: reqwest::Error) -> Error {
+    if e.is_timeout() {
+        Error::ActionFailed(FailureKind::Timeout, e.to_string())
+    } else if e.is_connect() {
+        Error::ActionFailed(FailureKind::Connect, e.to_string())
+    } else {
+        Error::Action(e.to_string())
+    }
+}
+
+/// Check a response status against the expected statuses, any 2xx status when none are configured
+fn is_expected_status(expected: Option<&[ExpectedStatus]>, status: u16) -> Result<bool, Error> {
+    match expected {
+        Some(expected) => matches_status(expected, status),
+        None => Ok((200..300).contains(&status)),
+    }
+}
+
+/// Check whether a status is one of the given codes, ranges or classes
+pub(super) fn matches_status(expected: &[ExpectedStatus], status: u16) -> Result<bool, Error> {
+    for expected_status in expected {
+        let matched = match expected_status {
+            ExpectedStatus::Code(code) => *code == status,
+            ExpectedStatus::Range(_) => expected_status
+                .range()
+                .map_err(|e| Error::Action(e.to_string()))?
+                .contains(&status),
+        };
+        if matched {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Truncate a response body to at most `max` bytes on a character boundary
+pub(super) fn truncate(mut body: String, max: usize) -> String {
+    if body.len() > max {
+        let mut end = max;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+        body.push_str("...");
+    }
+    body
+}
+
+/// Render templated fields, failing on the first field that does not render
+fn render_fields(
+    fields: &HashMap<String, String>,
+    context: &Context,
+) -> Result<HashMap<String, String>, Error> {
+    fields
+        .iter()
+        .map(|(name, value)| {
+            template::render_template(value, context)
+                .map(|rendered| (name.clone(), rendered))
+                .map_err(|e| Error::Action(format!("Failed to render field {}: {}", name, e)))
+        })
+        .collect()
+}
+
+/// Build a multipart form with rendered text fields and files read from disk.
+/// File paths are used as configured
+async fn build_multipart(
+    fields: &HashMap<String, MultipartField>,
+    context: &Context,
+) -> Result<Form, Error> {
+    let mut form = Form::new();
+
+    for (name, field) in fields {
+        form = match field {
+            MultipartField::Text(value) => {
+                let value = template::render_template(value, context).map_err(|e| {
+                    Error::Action(format!("Failed to render field {}: {}", name, e))
+                })?;
+                form.text(name.clone(), value)
+            }
+            MultipartField::File { file } => {
+                // the path is not templated, so that event data cannot choose the file
+                let path = tokio::fs::canonicalize(file)
+                    .await
+                    .map_err(|e| Error::Action(format!("Failed to read file {}: {}", file, e)))?;
+                let data = tokio::fs::read(&path)
+                    .await
+                    .map_err(|e| Error::Action(format!("Failed to read file {}: {}", file, e)))?;
+                let file_name = path
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                form.part(name.clone(), Part::bytes(data).file_name(file_name))
+            }
+        };
+    }
+
+    Ok(form)
+}
+
+/// Build an HTTP client with the action's timeout, redirect and TLS options
+fn build_client(action: &HttpAction) -> Result<Client, Error> {
+    let timeout = Duration::from_secs(action.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    let redirect_policy = match action.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS) {
+        0 => redirect::Policy::none(),
+        max => redirect::Policy::limited(max),
+    };
+
+    if action.insecure_skip_verify {
+        warn!("TLS verification disabled for HTTP action: {}", action.url);
+    }
+
+    Client::builder()
+        .timeout(timeout)
+        .redirect(redirect_policy)
+        .danger_accept_invalid_certs(action.insecure_skip_verify)
+        .build()
+        .map_err(|e| Error::Action(format!("Failed to create HTTP client: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::rules::BasicAuth;
+    use tempfile::tempdir;
+    use wiremock::matchers::{
+        body_json, body_string, body_string_contains, header, header_regex, method, path,
+        query_param,
+    };
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn create_test_http_action(url: String) -> HttpAction {
+        HttpAction {
+            method: "POST".to_string(),
+            url,
+            headers: Some(
+                [("X-Branch".to_string(), "{{ branch }}".to_string())]
+                    .into_iter()
+                    .collect(),
+            ),
+            body: Some("built {{ branch }}".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn create_test_context() -> Context {
+        let mut context = Context::new();
+        context.insert("branch", "main");
+        context
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_sends_rendered_request() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/build/main"))
+            .and(header("X-Branch", "main"))
+            .and(body_string("built main"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let action =
+            create_test_http_action(format!("{}/build/{{{{ branch }}}}", mock_server.uri()));
+
+        let result = exec_http_action(&action, &create_test_context(), false).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_dry_run_does_not_send() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let action =
+            create_test_http_action(format!("{}/build/{{{{ branch }}}}", mock_server.uri()));
+
+        let result = exec_http_action(&action, &create_test_context(), true).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_dry_run_fails_on_render_error() {
+        let action = create_test_http_action("http://localhost/{{ missing }}".to_string());
+
+        let result = exec_http_action(&action, &create_test_context(), true).await;
+        assert!(matches!(result, Err(Error::Action(_))));
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_methods() {
+        let mock_server = MockServer::start().await;
+        for http_method in ["PUT", "PATCH", "DELETE", "HEAD"] {
+            Mock::given(method(http_method))
+                .and(path("/jobs/main"))
+                .respond_with(ResponseTemplate::new(204))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        for http_method in ["put", "PATCH", "Delete", "HEAD"] {
+            let action = HttpAction {
+                method: http_method.to_string(),
+                url: format!("{}/jobs/{{{{ branch }}}}", mock_server.uri()),
+                ..Default::default()
+            };
+
+            let result = exec_http_action(&action, &create_test_context(), false)
+                .await
+                .unwrap();
+            assert_eq!(result.status, Some(204));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_unsupported_method() {
+        let action = HttpAction {
+            method: "TRACE".to_string(),
+            url: "http://localhost/".to_string(),
+            ..Default::default()
+        };
+
+        let result = exec_http_action(&action, &create_test_context(), true).await;
+        assert!(matches!(result, Err(Error::Action(_))));
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_query_and_bearer_auth() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/status"))
+            .and(query_param("branch", "main"))
+            .and(header("Authorization", "Bearer token-main"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let action = HttpAction {
+            method: "GET".to_string(),
+            url: format!("{}/status", mock_server.uri()),
+            query: Some(
+                [("branch".to_string(), "{{ branch }}".to_string())]
+                    .into_iter()
+                    .collect(),
+            ),
+            auth: Some(HttpAuth::Bearer {
+                bearer: "token-{{ branch }}".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let result = exec_http_action(&action, &create_test_context(), false).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_basic_auth() {
+        let mock_server = MockServer::start().await;
+        // base64 of "ci:main"
+        Mock::given(method("POST"))
+            .and(header("Authorization", "Basic Y2k6bWFpbg=="))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let action = HttpAction {
+            method: "POST".to_string(),
+            url: mock_server.uri(),
+            auth: Some(HttpAuth::Basic {
+                basic: BasicAuth {
+                    username: "ci".to_string(),
+                    password: Some("{{ branch }}".to_string()),
+                },
+            }),
+            ..Default::default()
+        };
+
+        let result = exec_http_action(&action, &create_test_context(), false).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_timeout() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(3)))
+            .mount(&mock_server)
+            .await;
+
+        let action = HttpAction {
+            method: "GET".to_string(),
+            url: mock_server.uri(),
+            timeout: Some(1),
+            ..Default::default()
+        };
+
+        let result = exec_http_action(&action, &create_test_context(), false).await;
+        assert!(matches!(
+            result,
+            Err(Error::ActionFailed(FailureKind::Timeout, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_redirects_disabled() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/old"))
+            .respond_with(ResponseTemplate::new(302).insert_header("Location", "/new"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/new"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let action = HttpAction {
+            method: "GET".to_string(),
+            url: format!("{}/old", mock_server.uri()),
+            max_redirects: Some(0),
+            expected_status: Some(vec![ExpectedStatus::Range("3xx".to_string())]),
+            ..Default::default()
+        };
+
+        let result = exec_http_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+        assert_eq!(result.status, Some(302));
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_unexpected_status_fails() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("x".repeat(2000)))
+            .mount(&mock_server)
+            .await;
+
+        let action =
+            create_test_http_action(format!("{}/build/{{{{ branch }}}}", mock_server.uri()));
+
+        let result = exec_http_action(&action, &create_test_context(), false).await;
+        match result {
+            Err(Error::ActionFailed(FailureKind::Status(500), message)) => {
+                assert!(message.contains("unexpected status 500"));
+                assert!(message.ends_with(&format!("{}...", "x".repeat(10))));
+                assert!(message.len() < 1200);
+            }
+            _ => panic!("expected an action error, got {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_result_has_status_and_body() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201).set_body_string("queued"))
+            .mount(&mock_server)
+            .await;
+
+        let action =
+            create_test_http_action(format!("{}/build/{{{{ branch }}}}", mock_server.uri()));
+
+        let result = exec_http_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+        assert_eq!(result.status, Some(201));
+        assert_eq!(result.body.as_deref(), Some("queued"));
+    }
+
+    #[test]
+    fn test_is_expected_status() {
+        // Setup
+        let expected = vec![
+            ExpectedStatus::Code(202),
+            ExpectedStatus::Range("300-303".to_string()),
+            ExpectedStatus::Range("4xx".to_string()),
+        ];
+
+        // Execute & Verify
+        assert!(is_expected_status(None, 200).unwrap());
+        assert!(is_expected_status(None, 299).unwrap());
+        assert!(!is_expected_status(None, 302).unwrap());
+        assert!(is_expected_status(Some(&expected), 202).unwrap());
+        assert!(!is_expected_status(Some(&expected), 200).unwrap());
+        assert!(is_expected_status(Some(&expected), 303).unwrap());
+        assert!(!is_expected_status(Some(&expected), 304).unwrap());
+        assert!(is_expected_status(Some(&expected), 404).unwrap());
+        assert!(!is_expected_status(Some(&expected), 500).unwrap());
+    }
+
+    #[test]
+    fn test_is_expected_status_invalid_range() {
+        for range in ["ok", "700xx", "6xx", "0xx", "2xx-", "500-200"] {
+            let expected = vec![ExpectedStatus::Range(range.to_string())];
+
+            let result = is_expected_status(Some(&expected), 200);
+            assert!(matches!(result, Err(Error::Action(_))), "{}", range);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_extracts_outputs() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("Location", "/deployments/42")
+                    .set_body_string(r#"{"deployment": {"id": 42, "env": "prod"}}"#),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let mut action =
+            create_test_http_action(format!("{}/build/{{{{ branch }}}}", mock_server.uri()));
+        action.outputs = Some(
+            [
+                (
+                    "id".to_string(),
+                    OutputExtractor {
+                        json: Some("/deployment/id".to_string()),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "env".to_string(),
+                    OutputExtractor {
+                        json: Some("/deployment/env".to_string()),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "location".to_string(),
+                    OutputExtractor {
+                        header: Some("Location".to_string()),
+                        regex: Some(r"/deployments/(\d+)".to_string()),
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let result = exec_http_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+
+        let outputs = result.outputs.unwrap();
+        assert_eq!(outputs.get("id").map(String::as_str), Some("42"));
+        assert_eq!(outputs.get("env").map(String::as_str), Some("prod"));
+        assert_eq!(outputs.get("location").map(String::as_str), Some("42"));
+    }
+
+    #[test]
+    fn test_extract_output_missing_value() {
+        // Setup
+        let headers = HeaderMap::new();
+        let json = OutputExtractor {
+            json: Some("/missing".to_string()),
+            ..Default::default()
+        };
+        let regex = OutputExtractor {
+            regex: Some("id=(\\w+)".to_string()),
+            ..Default::default()
+        };
+        let header = OutputExtractor {
+            header: Some("Location".to_string()),
+            ..Default::default()
+        };
+
+        // Execute & Verify
+        assert!(extract_output(&json, &headers, r#"{"id": 1}"#).is_err());
+        assert!(extract_output(&json, &headers, "not json").is_err());
+        assert!(extract_output(&regex, &headers, "no match").is_err());
+        assert_eq!(
+            extract_output(&regex, &headers, "created id=abc"),
+            Ok("abc".to_string())
+        );
+        assert!(extract_output(&header, &headers, "").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_json_body() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("Content-Type", "application/json"))
+            .and(body_json(serde_json::json!({
+                "title": r#"Fix "quoted" title"#,
+                "branches": ["main"],
+                "draft": false,
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut context = create_test_context();
+        context.insert("title", r#"Fix "quoted" title"#);
+        let action = HttpAction {
+            method: "POST".to_string(),
+            url: mock_server.uri(),
+            json: Some(serde_json::json!({
+                "title": "{{ title }}",
+                "branches": ["{{ branch }}"],
+                "draft": false,
+            })),
+            ..Default::default()
+        };
+
+        let result = exec_http_action(&action, &context, false).await;
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_form_body() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("Content-Type", "application/x-www-form-urlencoded"))
+            .and(body_string("branch=main+branch"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let action = HttpAction {
+            method: "POST".to_string(),
+            url: mock_server.uri(),
+            form: Some(
+                [("branch".to_string(), "{{ branch }} branch".to_string())]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+
+        let result = exec_http_action(&action, &create_test_context(), false).await;
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_multipart_body() {
+        let dir = tempdir().unwrap();
+        let report = dir.path().join("report.xml");
+        std::fs::write(&report, "<testsuite/>").unwrap();
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header_regex(
+                "Content-Type",
+                "^multipart/form-data; boundary=",
+            ))
+            .and(body_string_contains("name=\"branch\"\r\n\r\nmain"))
+            .and(body_string_contains("filename=\"report.xml\""))
+            .and(body_string_contains("<testsuite/>"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let action = HttpAction {
+            method: "POST".to_string(),
+            url: mock_server.uri(),
+            multipart: Some(
+                [
+                    (
+                        "branch".to_string(),
+                        MultipartField::Text("{{ branch }}".to_string()),
+                    ),
+                    (
+                        "report".to_string(),
+                        MultipartField::File {
+                            file: report.display().to_string(),
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        };
+
+        let result = exec_http_action(&action, &create_test_context(), false).await;
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_exec_http_action_multiple_bodies_rejected() {
+        let mut action = create_test_http_action("http://localhost/".to_string());
+        action.json = Some(serde_json::json!({"branch": "{{ branch }}"}));
+
+        let result = exec_http_action(&action, &create_test_context(), true).await;
+        assert!(matches!(result, Err(Error::Action(_))));
+    }
+}
@@ -0,0 +1,1307 @@
+pub mod bitbucket;
+pub mod chat;
+pub mod http;
+pub mod retry;
+pub mod shell;
+
+use crate::app::{
+    config::{rules::BuildState, Action, Rule},
+    template, Error,
+};
+use futures::stream::{self, FuturesUnordered, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use tera::Context;
+use tokio::sync::Semaphore;
+use tracing::{debug, info, warn};
+
+pub use bitbucket::exec_bitbucket_action;
+pub use chat::{exec_slack_action, exec_teams_action};
+pub use http::exec_http_action;
+pub use shell::exec_shell_action;
+
+/// Outcome of an executed action
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ActionResult {
+    /// Type of the action, e.g. "http", "shell", "bitbucket", "slack" or "teams"
+    pub action: String,
+
+    /// Method and URL of the HTTP request, the shell command, the Bitbucket
+    /// operations, or the chat webhook URL, rendered without environment variables
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+
+    /// Item of the action's `foreach` the action ran for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<Value>,
+
+    /// Whether the action was only rendered and logged
+    pub dry_run: bool,
+
+    /// Whether the action did not run because its `if` condition was false
+    pub skipped: bool,
+
+    /// Time taken by the action, retries included
+    pub duration_ms: u64,
+
+    /// HTTP response status code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    /// HTTP response body, truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+
+    /// Values extracted from the HTTP response by output name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<HashMap<String, String>>,
+
+    /// Shell command exit code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+
+    /// Shell command standard output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stdout: Option<String>,
+
+    /// Shell command standard error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+
+    /// Why the action failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ActionResult {
+    fn dry_run(action: &str) -> Self {
+        Self {
+            action: action.to_string(),
+            dry_run: true,
+            ..Default::default()
+        }
+    }
+
+    fn skipped(action: &str) -> Self {
+        Self {
+            action: action.to_string(),
+            skipped: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Execute an action, retrying failed attempts as allowed by the action's retry
+/// policy. In dry run mode the action is rendered and logged but not sent
+pub async fn exec_action(
+    action: &Action,
+    context: &Context,
+    dry_run: bool,
+) -> Result<Vec<ActionResult>, Error> {
+    let Some(policy) = &action.retry else {
+        return exec_action_once(action, context, dry_run).await;
+    };
+    let max_attempts = retry::max_attempts(policy);
+
+    let mut attempt = 1;
+    loop {
+        match exec_action_once(action, context, dry_run).await {
+            Err(e) if attempt < max_attempts && retry::is_retryable(policy, &e) => {
+                let delay = retry::backoff(policy, attempt);
+                warn!(
+                    "Action attempt {} of {} failed, retrying in {:?}: {:?}",
+                    attempt, max_attempts, delay, e
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Execute an action once per item, each item holding one of the rule's
+/// `permits` while it runs, with the item as `item` in the context. The first
+/// failed item fails the action
+async fn exec_action_items(
+    action: &Action,
+    context: &Context,
+    items: Vec<Value>,
+    dry_run: bool,
+    permits: &Semaphore,
+) -> Result<Vec<ActionResult>, Error> {
+    // every item is queued, the permits limit how many run at a time
+    let queued = items.len().max(1);
+    let outcomes: Vec<Result<Vec<ActionResult>, Error>> = stream::iter(items)
+        .map(|item| async move {
+            let mut context = context.clone();
+            context.insert("item", &item);
+            let targets = action_targets(&render_action(action, &context));
+
+            let _permit = permits.acquire().await;
+            let started = Instant::now();
+            let outcome = exec_action(action, &context, dry_run).await;
+            let duration_ms = started.elapsed().as_millis() as u64;
+
+            let mut results = outcome.map_err(|e| match e {
+                Error::ActionFailed(kind, message) => {
+                    Error::ActionFailed(kind, format!("item {}: {}", item, message))
+                }
+                Error::Action(message) => Error::Action(format!("item {}: {}", item, message)),
+                other => other,
+            })?;
+            for (result, (_, target)) in results.iter_mut().zip(targets) {
+                result.target = Some(target);
+                result.item = Some(item.clone());
+                result.duration_ms = duration_ms;
+            }
+            Ok(results)
+        })
+        .buffered(queued)
+        .collect()
+        .await;
+
+    let mut results = Vec::new();
+    for outcome in outcomes {
+        results.extend(outcome?);
+    }
+    Ok(results)
+}
+
+/// Execute an action once
+async fn exec_action_once(
+    action: &Action,
+    context: &Context,
+    dry_run: bool,
+) -> Result<Vec<ActionResult>, Error> {
+    let mut results = Vec::new();
+
+    if let Some(http) = &action.http {
+        // TODO tracing
+        results.push(exec_http_action(http, context, dry_run).await?);
+    }
+    if let Some(shell) = &action.shell {
+        results.push(exec_shell_action(shell, context, dry_run).await?);
+    }
+    if let Some(bitbucket) = &action.bitbucket {
+        results.push(exec_bitbucket_action(bitbucket, context, dry_run).await?);
+    }
+    if let Some(slack) = &action.slack {
+        results.push(exec_slack_action(slack, context, dry_run).await?);
+    }
+    if let Some(teams) = &action.teams {
+        results.push(exec_teams_action(teams, context, dry_run).await?);
+    }
+
+    Ok(results)
+}
+
+/// Failed action of a rule
+#[derive(Debug)]
+pub struct FailedAction {
+    /// Position of the action in the rule's actions, or in its hook
+    pub index: usize,
+    /// Action with its templates rendered, see `render_action`
+    pub request: Value,
+    pub error: Error,
+    /// Results of the finished actions in declaration order, the failed one last
+    pub results: Vec<ActionResult>,
+}
+
+impl From<FailedAction> for Error {
+    fn from(failed: FailedAction) -> Self {
+        failed.error
+    }
+}
+
+/// Execute the actions of a rule, then its hooks: `on_success` when the actions
+/// succeeded, `on_failure` with the failed action as `failure` in the context
+/// otherwise, and `always` in both cases. A failed hook fails a rule that
+/// succeeded so far; after a failure, hook failures are only reported.
+/// With `report_status`, the rule's state is posted before and after
+pub async fn exec_rule_actions(
+    rule_name: &str,
+    rule: &Rule,
+    mut context: Context,
+) -> Result<Vec<ActionResult>, FailedAction> {
+    let dry_run = rule.dry_run;
+    if dry_run {
+        info!("Dry run of actions for rule: {}", rule_name);
+    } else {
+        debug!("Executing actions for rule: {}", rule_name);
+    }
+    template::insert_rule_context(&mut context, rule_name);
+
+    report_status(rule_name, rule, &context, BuildState::Inprogress, None).await;
+
+    // one limit for the actions and the items of their foreach
+    let max_parallel = rule.max_parallel().max(1);
+    let permits = Semaphore::new(max_parallel);
+    let mut outcome = exec_actions(
+        rule_name,
+        &rule.actions,
+        dry_run,
+        max_parallel,
+        &permits,
+        &mut context,
+    )
+    .await;
+    match &outcome {
+        Ok(_) => {
+            let hook = exec_actions(
+                rule_name,
+                &rule.on_success,
+                dry_run,
+                max_parallel,
+                &permits,
+                &mut context,
+            )
+            .await;
+            outcome = merge_hook(outcome, hook);
+        }
+        Err(failed) => {
+            let id = rule.actions.get(failed.index).and_then(|a| a.id.as_deref());
+            template::insert_failure_context(&mut context, failed, id);
+            let hook = exec_actions(
+                rule_name,
+                &rule.on_failure,
+                dry_run,
+                max_parallel,
+                &permits,
+                &mut context,
+            )
+            .await;
+            outcome = merge_hook(outcome, hook);
+        }
+    }
+    let hook = exec_actions(
+        rule_name,
+        &rule.always,
+        dry_run,
+        max_parallel,
+        &permits,
+        &mut context,
+    )
+    .await;
+    let outcome = merge_hook(outcome, hook);
+
+    match &outcome {
+        Ok(_) => report_status(rule_name, rule, &context, BuildState::Successful, None).await,
+        Err(failed) => {
+            let error = failed.error.to_string();
+            report_status(rule_name, rule, &context, BuildState::Failed, Some(&error)).await
+        }
+    }
+
+    outcome
+}
+
+/// Report the state of a rule run as a commit build status when the rule has
+/// `report_status`. A status that cannot be reported is logged, it does not
+/// fail the rule
+async fn report_status(
+    rule_name: &str,
+    rule: &Rule,
+    context: &Context,
+    state: BuildState,
+    error: Option<&str>,
+) {
+    if !rule.report_status {
+        return;
+    }
+    if rule.dry_run {
+        info!("Dry run status of rule {}: {:?}", rule_name, state);
+        return;
+    }
+
+    if let Err(e) = bitbucket::report_rule_status(rule_name, context, state, error).await {
+        warn!("Failed to report status of rule {}: {}", rule_name, e);
+    }
+}
+
+/// Add the results of hook actions to the outcome of a rule
+fn merge_hook(
+    outcome: Result<Vec<ActionResult>, FailedAction>,
+    hook: Result<Vec<ActionResult>, FailedAction>,
+) -> Result<Vec<ActionResult>, FailedAction> {
+    match (outcome, hook) {
+        (Ok(mut results), Ok(hook_results)) => {
+            results.extend(hook_results);
+            Ok(results)
+        }
+        (Ok(mut results), Err(mut failed)) => {
+            results.append(&mut failed.results);
+            failed.results = results;
+            Err(failed)
+        }
+        (Err(mut failed), Ok(hook_results)) => {
+            failed.results.extend(hook_results);
+            Err(failed)
+        }
+        (Err(mut failed), Err(hook_failed)) => {
+            warn!(
+                "Hook action failed after a failed action: {}",
+                hook_failed.error
+            );
+            failed.results.extend(hook_failed.results);
+            Err(failed)
+        }
+    }
+}
+
+/// Execute actions, up to `max_parallel` at a time. Each action, or each item of
+/// an action with a `foreach`, holds one of the `permits` while it runs, so that
+/// at most `max_parallel` requests or commands run at once. An action starts once
+/// the actions it needs finished, in declaration order otherwise, and is rendered
+/// with the outputs of the finished actions as `steps.<id>.outputs`. A failed
+/// action stops new actions from starting, unless it continues on error
+async fn exec_actions(
+    rule_name: &str,
+    actions: &[Action],
+    dry_run: bool,
+    max_parallel: usize,
+    permits: &Semaphore,
+    context: &mut Context,
+) -> Result<Vec<ActionResult>, FailedAction> {
+    let mut results = vec![Vec::new(); actions.len()];
+    let mut started = vec![false; actions.len()];
+    let mut finished: HashSet<&str> = HashSet::new();
+    let mut failure: Option<FailedAction> = None;
+    let mut running = FuturesUnordered::new();
+
+    loop {
+        // start the actions whose needs finished, in declaration order
+        for (index, action) in actions.iter().enumerate() {
+            if failure.is_some() || running.len() >= max_parallel {
+                break;
+            }
+            let ready = action
+                .needs
+                .iter()
+                .flatten()
+                .all(|need| finished.contains(need.as_str()));
+            if started[index] || !ready {
+                continue;
+            }
+
+            started[index] = true;
+            let request = render_action(action, context);
+            let action_context = context.clone();
+
+            // the condition sees the outputs of the finished actions
+            let condition = action.condition.as_deref().map(|condition| {
+                template::evaluate_condition(condition, context).map_err(|e| {
+                    Error::Action(format!("Failed to evaluate condition {}: {}", condition, e))
+                })
+            });
+            let skipped: Vec<_> = action_targets(&request)
+                .iter()
+                .map(|(action, _)| ActionResult::skipped(action))
+                .collect();
+            let items = action.foreach.as_deref().map(|foreach| {
+                template::render_items(foreach, context).map_err(|e| {
+                    Error::Action(format!("Failed to render foreach {}: {}", foreach, e))
+                })
+            });
+
+            running.push(async move {
+                let started = Instant::now();
+                let outcome = match (condition, items) {
+                    (Some(Ok(false)), _) => Ok(skipped),
+                    (Some(Err(e)), _) | (_, Some(Err(e))) => Err(e),
+                    (_, Some(Ok(items))) => {
+                        exec_action_items(action, &action_context, items, dry_run, permits).await
+                    }
+                    (_, None) => {
+                        let _permit = permits.acquire().await;
+                        exec_action(action, &action_context, dry_run).await
+                    }
+                };
+                (
+                    index,
+                    request,
+                    started.elapsed().as_millis() as u64,
+                    outcome,
+                )
+            });
+        }
+
+        let Some((index, request, duration_ms, outcome)) = running.next().await else {
+            break;
+        };
+        let action = &actions[index];
+        let targets = action_targets(&request);
+
+        let error = match outcome {
+            Ok(mut action_results) => {
+                for (position, result) in action_results.iter_mut().enumerate() {
+                    // foreach results carry the target and duration of their item
+                    if result.item.is_none() {
+                        result.target = targets.get(position).map(|(_, target)| target.clone());
+                        result.duration_ms = duration_ms;
+                    }
+                    info!(
+                        rule = rule_name,
+                        action = %result.action,
+                        target = result.target.as_deref().unwrap_or_default(),
+                        status = result.status.map(i64::from).or(result.exit_code.map(i64::from)),
+                        duration_ms,
+                        "Action {}",
+                        if result.skipped { "skipped" } else { "succeeded" }
+                    );
+                }
+                debug!("Action results: {:?}", action_results);
+
+                if let Some(id) = &action.id {
+                    let outputs = action_results
+                        .iter()
+                        .filter_map(|result| result.outputs.clone())
+                        .flatten()
+                        .collect();
+                    template::insert_step_context(context, id, &outputs);
+                    finished.insert(id);
+                }
+                results[index] = action_results;
+                continue;
+            }
+            Err(error) => error,
+        };
+
+        let (types, targets): (Vec<_>, Vec<_>) = targets.into_iter().unzip();
+        let result = ActionResult {
+            action: types.join("+"),
+            target: Some(targets.join(", ")),
+            dry_run,
+            duration_ms,
+            error: Some(error.to_string()),
+            ..Default::default()
+        };
+        info!(
+            rule = rule_name,
+            action = %result.action,
+            target = result.target.as_deref().unwrap_or_default(),
+            duration_ms,
+            error = %error,
+            "Action failed"
+        );
+
+        if action.continue_on_error || failure.is_some() {
+            warn!(
+                "Action {} of rule {} failed, continuing: {}",
+                index, rule_name, error
+            );
+            if let Some(id) = &action.id {
+                finished.insert(id);
+            }
+            results[index] = vec![result];
+        } else {
+            failure = Some(FailedAction {
+                index,
+                request,
+                error,
+                results: vec![result],
+            });
+        }
+    }
+
+    let mut rule_results: Vec<ActionResult> = results.into_iter().flatten().collect();
+    if let Some(mut failed) = failure {
+        rule_results.append(&mut failed.results);
+        failed.results = rule_results;
+        return Err(failed);
+    }
+
+    // actions needing an action that does not exist never start
+    if let Some(index) = started.iter().position(|started| !started) {
+        return Err(FailedAction {
+            index,
+            request: render_action(&actions[index], context),
+            error: Error::Action(format!(
+                "action {} needs actions that did not run: {:?}",
+                index,
+                actions[index].needs.as_deref().unwrap_or_default()
+            )),
+            results: rule_results,
+        });
+    }
+
+    Ok(rule_results)
+}
+
+/// Type and target of each part of a rendered action, in execution order
+fn action_targets(request: &Value) -> Vec<(String, String)> {
+    let mut targets = Vec::new();
+
+    let http = &request["http"];
+    if !http.is_null() {
+        let method = http["method"].as_str().unwrap_or_default().to_uppercase();
+        let url = http["url"].as_str().unwrap_or_default();
+        targets.push(("http".to_string(), format!("{} {}", method, url)));
+    }
+
+    let shell = &request["shell"];
+    if !shell.is_null() {
+        let command = match shell["argv"].as_array() {
+            Some(argv) => argv
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => shell["command"].as_str().unwrap_or_default().to_string(),
+        };
+        targets.push(("shell".to_string(), command));
+    }
+
+    let bitbucket = &request["bitbucket"];
+    if !bitbucket.is_null() {
+        let mut operations = Vec::new();
+        if !bitbucket["comment"].is_null() {
+            operations.push("comment".to_string());
+        }
+        if let Some(state) = bitbucket["build_status"]["state"].as_str() {
+            operations.push(format!("build_status {}", state));
+        }
+        if let Some(key) = bitbucket["insights"]["key"].as_str() {
+            operations.push(format!("insights {}", key));
+        }
+        if let Some(reviewers) = bitbucket["reviewers"].as_array() {
+            let reviewers: Vec<_> = reviewers.iter().filter_map(Value::as_str).collect();
+            operations.push(format!("reviewers {}", reviewers.join(" ")));
+        }
+        if let Some(operation) = bitbucket["pull_request"].as_str() {
+            operations.push(operation.to_string());
+        }
+        targets.push(("bitbucket".to_string(), operations.join(", ")));
+    }
+
+    // incoming webhook URLs are credentials, they are not reported
+    for chat in ["slack", "teams"] {
+        if !request[chat].is_null() {
+            targets.push((chat.to_string(), "incoming webhook".to_string()));
+        }
+    }
+
+    targets
+}
+
+/// Render the templates of an action for inspection. Environment variables are
+/// left out so that secrets are not rendered, and incoming webhook URLs are
+/// redacted; templates that do not render are kept as they are
+pub fn render_action(action: &Action, context: &Context) -> Value {
+    let mut context = context.clone();
+    context.remove("env");
+
+    fn render(value: Value, context: &Context) -> Value {
+        match value {
+            Value::String(template_str) => Value::String(
+                template::render_template(&template_str, context).unwrap_or(template_str),
+            ),
+            Value::Array(items) => items
+                .into_iter()
+                .map(|item| render(item, context))
+                .collect(),
+            Value::Object(fields) => fields
+                .into_iter()
+                .map(|(key, field)| (key, render(field, context)))
+                .collect(),
+            other => other,
+        }
+    }
+
+    let mut request = render(serde_json::to_value(action).unwrap_or_default(), &context);
+    for chat in ["slack", "teams"] {
+        if let Some(chat) = request.get_mut(chat).and_then(Value::as_object_mut) {
+            chat.insert("webhook_url".to_string(), Value::from("[redacted]"));
+        }
+    }
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::rules::{HttpAction, OutputExtractor, RetryPolicy, SlackAction};
+    use serde_json::json;
+    use std::env;
+    use std::time::Duration;
+    use wiremock::matchers::{body_partial_json, body_string, body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_render_action_redacts_webhook_urls() {
+        // Setup
+        let action = Action {
+            slack: Some(SlackAction {
+                webhook_url: "https://hooks.slack.com/services/T0/B0/secret".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Execute
+        let request = render_action(&action, &Context::new());
+        let targets = action_targets(&request);
+
+        // Verify
+        assert!(!request.to_string().contains("secret"));
+        assert_eq!(
+            targets,
+            vec![("slack".to_string(), "incoming webhook".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_passes_step_outputs() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/deployments"))
+            .respond_with(ResponseTemplate::new(201).set_body_string(r#"{"id": "d-42"}"#))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/comments"))
+            .and(body_string("deployed d-42"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let rule = Rule {
+            actions: vec![
+                Action {
+                    id: Some("deploy".to_string()),
+                    http: Some(HttpAction {
+                        method: "POST".to_string(),
+                        url: format!("{}/deployments", mock_server.uri()),
+                        outputs: Some(
+                            [(
+                                "id".to_string(),
+                                OutputExtractor {
+                                    json: Some("/id".to_string()),
+                                    ..Default::default()
+                                },
+                            )]
+                            .into_iter()
+                            .collect(),
+                        ),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                Action {
+                    http: Some(HttpAction {
+                        method: "POST".to_string(),
+                        url: format!("{}/comments", mock_server.uri()),
+                        body: Some("deployed {{ steps.deploy.outputs.id }}".to_string()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_rule_actions("deploy", &rule, Context::new()).await;
+
+        // Verify
+        let results = result.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].status, Some(201));
+        assert_eq!(
+            results[1].target,
+            Some(format!("POST {}/comments", mock_server.uri()))
+        );
+        assert!(results.iter().all(|result| result.error.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_continues_on_error() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/notify"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/deploy"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let action = |url: String, continue_on_error| Action {
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url,
+                ..Default::default()
+            }),
+            continue_on_error,
+            ..Default::default()
+        };
+        let rule = Rule {
+            actions: vec![
+                action(format!("{}/notify", mock_server.uri()), true),
+                action(format!("{}/deploy", mock_server.uri()), false),
+                action(format!("{}/notify", mock_server.uri()), false),
+            ],
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_rule_actions("deploy", &rule, Context::new()).await;
+
+        // Verify
+        let failed = result.unwrap_err();
+        assert_eq!(failed.index, 2);
+        assert_eq!(failed.results.len(), 3);
+        assert!(failed.results[0].error.is_some());
+        assert_eq!(failed.results[1].status, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_runs_failure_hooks() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/deploy"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/alert"))
+            .and(body_string("deploy failed with 503"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/cleanup"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/celebrate"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let action = |path: &str, body: Option<&str>| Action {
+            id: Some(path.to_string()),
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url: format!("{}/{}", mock_server.uri(), path),
+                body: body.map(str::to_string),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let rule = Rule {
+            actions: vec![action("deploy", None)],
+            on_success: vec![action("celebrate", None)],
+            on_failure: vec![action(
+                "alert",
+                Some("{{ failure.id }} failed with {{ failure.status }}"),
+            )],
+            always: vec![action("cleanup", None)],
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_rule_actions("deploy", &rule, Context::new()).await;
+
+        // Verify
+        let failed = result.unwrap_err();
+        assert_eq!(failed.index, 0);
+        assert!(matches!(
+            failed.error,
+            Error::ActionFailed(crate::app::FailureKind::Status(503), _)
+        ));
+        let targets: Vec<_> = failed
+            .results
+            .iter()
+            .filter_map(|result| result.target.as_deref())
+            .map(|target| target.rsplit('/').next().unwrap())
+            .collect();
+        assert_eq!(targets, vec!["deploy", "alert", "cleanup"]);
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_fails_on_success_hook_failure() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/notify"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let action = |path: &str| Action {
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url: format!("{}/{}", mock_server.uri(), path),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let rule = Rule {
+            actions: vec![action("deploy")],
+            on_success: vec![action("notify")],
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_rule_actions("deploy", &rule, Context::new()).await;
+
+        // Verify
+        let failed = result.unwrap_err();
+        assert_eq!(failed.results.len(), 2);
+        assert_eq!(failed.results[0].status, Some(200));
+        assert!(failed.results[1].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_reports_status() {
+        // Setup
+        env::set_var("GIT_ACTIONS_TEST_BITBUCKET_TOKEN", "bitbucket-token");
+        let mock_server = MockServer::start().await;
+        let builds = "/api/latest/projects/PRJ/repos/repo/commits/abc123/builds";
+        for (state, description) in [("INPROGRESS", "Running"), ("FAILED", "Failed: ")] {
+            Mock::given(method("POST"))
+                .and(path(builds))
+                .and(body_partial_json(json!({"state": state, "key": "deploy"})))
+                .and(body_string_contains(description))
+                .respond_with(ResponseTemplate::new(204))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/deploy"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+
+        let mut context = Context::new();
+        context.insert(
+            "event",
+            &json!({"pull_request": {"id": 7, "commit": "abc123", "link": "https://bitbucket/pr/7"}}),
+        );
+        context.insert(
+            "webhook",
+            &json!({"name": "bitbucket", "bitbucket": {"api": {
+                "baseUrl": mock_server.uri(),
+                "project": "PRJ",
+                "repo": "repo",
+                "auth": {"type": "token", "tokenFromEnv": "GIT_ACTIONS_TEST_BITBUCKET_TOKEN"}
+            }}}),
+        );
+        let rule = Rule {
+            report_status: true,
+            actions: vec![Action {
+                http: Some(HttpAction {
+                    method: "POST".to_string(),
+                    url: format!("{}/deploy", mock_server.uri()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_rule_actions("deploy", &rule, context).await;
+
+        // Verify
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_runs_independent_actions_in_parallel() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/build"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"id": "b-1"}"#)
+                    .set_delay(std::time::Duration::from_millis(500)),
+            )
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/report"))
+            .and(body_string("b-1 b-1 b-1"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let build = |id: &str| Action {
+            id: Some(id.to_string()),
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url: format!("{}/build", mock_server.uri()),
+                outputs: Some(
+                    [(
+                        "id".to_string(),
+                        OutputExtractor {
+                            json: Some("/id".to_string()),
+                            ..Default::default()
+                        },
+                    )]
+                    .into_iter()
+                    .collect(),
+                ),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let report = Action {
+            needs: Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]),
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url: format!("{}/report", mock_server.uri()),
+                body: Some(
+                    "{{ steps.a.outputs.id }} {{ steps.b.outputs.id }} {{ steps.c.outputs.id }}"
+                        .to_string(),
+                ),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let rule = Rule {
+            // the dependent action is declared first and still waits
+            actions: vec![report, build("a"), build("b"), build("c")],
+            max_parallel: Some(3),
+            ..Default::default()
+        };
+
+        // Execute
+        let started = Instant::now();
+        let result = exec_rule_actions("build", &rule, Context::new()).await;
+
+        // Verify
+        let results = result.unwrap();
+        assert_eq!(results.len(), 4);
+        assert!(
+            started.elapsed() < std::time::Duration::from_millis(1400),
+            "{:?}",
+            started.elapsed()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_reports_unresolved_needs() {
+        let rule = Rule {
+            actions: vec![Action {
+                needs: Some(vec!["missing".to_string()]),
+                shell: Some(crate::app::config::rules::ShellAction {
+                    command: "true".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let result = exec_rule_actions("rule", &rule, Context::new()).await;
+
+        let failed = result.unwrap_err();
+        assert_eq!(failed.index, 0);
+        assert!(matches!(failed.error, Error::Action(_)));
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_skips_actions_by_condition() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/release"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/deploy"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut context = Context::new();
+        context.insert("event", &serde_json::json!({"branch": "feature/a"}));
+        let action = |path: &str, condition: &str| Action {
+            id: Some(path.to_string()),
+            condition: Some(condition.to_string()),
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url: format!("{}/{}", mock_server.uri(), path),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut deploy = action("deploy", r#"event.branch is starting_with("feature/")"#);
+        deploy.needs = Some(vec!["release".to_string()]);
+        let rule = Rule {
+            actions: vec![action("release", r#"event.branch == "main""#), deploy],
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_rule_actions("rule", &rule, context).await;
+
+        // Verify
+        let results = result.unwrap();
+        assert!(results[0].skipped);
+        assert_eq!(results[0].status, None);
+        assert!(!results[1].skipped);
+        assert_eq!(results[1].status, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_runs_action_per_item() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/build/api"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/build/web"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut context = Context::new();
+        context.insert("services", &vec!["api", "web"]);
+        let rule = Rule {
+            actions: vec![Action {
+                foreach: Some("{{ services | json_encode() }}".to_string()),
+                http: Some(HttpAction {
+                    method: "POST".to_string(),
+                    url: format!("{}/build/{{{{ item }}}}", mock_server.uri()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            max_parallel: Some(2),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_rule_actions("build", &rule, context).await;
+
+        // Verify
+        let results = result.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].item, Some(serde_json::json!("api")));
+        assert_eq!(
+            results[1].target,
+            Some(format!("POST {}/build/web", mock_server.uri()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_shares_limit_with_items() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+            .expect(6)
+            .mount(&mock_server)
+            .await;
+
+        let mut context = Context::new();
+        context.insert("services", &vec!["api", "web", "worker"]);
+        let action = Action {
+            foreach: Some("{{ services | json_encode() }}".to_string()),
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url: format!("{}/build/{{{{ item }}}}", mock_server.uri()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let rule = Rule {
+            actions: vec![action.clone(), action],
+            max_parallel: Some(2),
+            ..Default::default()
+        };
+
+        // Execute
+        let started = Instant::now();
+        let result = exec_rule_actions("build", &rule, context).await;
+
+        // Verify: 6 requests, 2 at a time
+        assert_eq!(result.unwrap().len(), 6);
+        assert!(started.elapsed() >= Duration::from_millis(550));
+    }
+
+    #[tokio::test]
+    async fn test_exec_action_retries_retryable_failures() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let action = Action {
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url: mock_server.uri(),
+                ..Default::default()
+            }),
+            retry: Some(RetryPolicy {
+                initial_backoff_ms: Some(10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Execute
+        let results = exec_action(&action, &Context::new(), false).await.unwrap();
+
+        // Verify
+        assert_eq!(results[0].status, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_exec_action_stops_after_max_attempts() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let action = Action {
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url: mock_server.uri(),
+                ..Default::default()
+            }),
+            retry: Some(RetryPolicy {
+                max_attempts: Some(2),
+                initial_backoff_ms: Some(10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_action(&action, &Context::new(), false).await;
+
+        // Verify
+        assert!(matches!(
+            result,
+            Err(Error::ActionFailed(crate::app::FailureKind::Status(500), _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_exec_action_does_not_retry_other_failures() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let action = Action {
+            http: Some(HttpAction {
+                method: "POST".to_string(),
+                url: mock_server.uri(),
+                ..Default::default()
+            }),
+            retry: Some(RetryPolicy {
+                initial_backoff_ms: Some(10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_action(&action, &Context::new(), false).await;
+
+        // Verify
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exec_rule_actions_reports_failed_action() {
+        // Setup
+        env::set_var("GIT_ACTIONS_TEST_TOKEN", "secret");
+        let mut context = Context::new();
+        context.insert("branch", "main");
+        template::insert_env_context(&mut context);
+        let rule = Rule {
+            actions: vec![Action {
+                http: Some(HttpAction {
+                    method: "DELETE".to_string(),
+                    url: "http://localhost/{{ branch }}".to_string(),
+                    headers: Some(
+                        [(
+                            "Authorization".to_string(),
+                            "Bearer {{ env.GIT_ACTIONS_TEST_TOKEN }}".to_string(),
+                        )]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    body: Some("{{ missing }}".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Execute
+        let result = exec_rule_actions("rule", &rule, context).await;
+
+        // Verify
+        let failed = result.unwrap_err();
+        assert_eq!(failed.index, 0);
+        assert_eq!(failed.request["http"]["url"], "http://localhost/main");
+        assert_eq!(
+            failed.request["http"]["headers"]["Authorization"],
+            "Bearer {{ env.GIT_ACTIONS_TEST_TOKEN }}"
+        );
+        assert!(matches!(failed.error, Error::Action(_)));
+        assert_eq!(failed.results.len(), 1);
+        assert_eq!(failed.results[0].action, "http");
+        assert_eq!(
+            failed.results[0].target.as_deref(),
+            Some("DELETE http://localhost/main")
+        );
+        assert!(failed.results[0].error.is_some());
+        env::remove_var("GIT_ACTIONS_TEST_TOKEN");
+    }
+}
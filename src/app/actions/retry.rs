@@ -0,0 +1,152 @@
+use super::http::matches_status;
+use crate::app::{
+    config::rules::{RetryPolicy, RetryableError},
+    Error, FailureKind,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const DEFAULT_INITIAL_BACKOFF_MS: u64 = 1000;
+const DEFAULT_MAX_BACKOFF_MS: u64 = 60_000;
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_JITTER: f64 = 0.1;
+const DEFAULT_ERRORS: [RetryableError; 2] = [RetryableError::Timeout, RetryableError::Connect];
+
+/// Maximum number of attempts of an action, including the first one
+pub fn max_attempts(policy: &RetryPolicy) -> u32 {
+    policy.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1)
+}
+
+/// Whether a failed attempt is retried under the policy. Only classified
+/// failures are retried, render and configuration errors are not
+pub fn is_retryable(policy: &RetryPolicy, error: &Error) -> bool {
+    let Error::ActionFailed(kind, _) = error else {
+        return false;
+    };
+    let errors = policy.errors.as_deref().unwrap_or(&DEFAULT_ERRORS);
+
+    match kind {
+        FailureKind::Status(status) => match &policy.status {
+            Some(statuses) => matches_status(statuses, *status).unwrap_or_else(|e| {
+                warn!("Invalid retry status: {:?}", e);
+                false
+            }),
+            None => *status == 429 || (500..600).contains(status),
+        },
+        FailureKind::Timeout => errors.contains(&RetryableError::Timeout),
+        FailureKind::Connect => errors.contains(&RetryableError::Connect),
+        FailureKind::Exit(_) => errors.contains(&RetryableError::Exit),
+    }
+}
+
+/// Backoff before a retry, 1 being the first retry. The backoff grows by the
+/// multiplier up to the maximum, then the jitter spreads it randomly
+pub fn backoff(policy: &RetryPolicy, retry: u32) -> Duration {
+    let initial = policy
+        .initial_backoff_ms
+        .unwrap_or(DEFAULT_INITIAL_BACKOFF_MS) as f64;
+    let max = policy.max_backoff_ms.unwrap_or(DEFAULT_MAX_BACKOFF_MS) as f64;
+    let multiplier = policy.multiplier.unwrap_or(DEFAULT_MULTIPLIER).max(1.0);
+    let jitter = policy.jitter.unwrap_or(DEFAULT_JITTER).clamp(0.0, 1.0);
+
+    let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+    let backoff = (initial * multiplier.powi(exponent)).min(max);
+    let spread = backoff * jitter * (2.0 * random_fraction() - 1.0);
+
+    Duration::from_millis((backoff + spread).max(0.0) as u64)
+}
+
+/// Fraction in [0, 1) taken from the clock, enough to spread retries apart
+fn random_fraction() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    nanos as f64 / 1_000_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::rules::ExpectedStatus;
+
+    fn failed(kind: FailureKind) -> Error {
+        Error::ActionFailed(kind, "failed".to_string())
+    }
+
+    #[test]
+    fn test_is_retryable_defaults() {
+        // Setup
+        let policy = RetryPolicy::default();
+
+        // Execute & Verify
+        assert!(is_retryable(&policy, &failed(FailureKind::Timeout)));
+        assert!(is_retryable(&policy, &failed(FailureKind::Connect)));
+        assert!(is_retryable(&policy, &failed(FailureKind::Status(503))));
+        assert!(is_retryable(&policy, &failed(FailureKind::Status(429))));
+        assert!(!is_retryable(&policy, &failed(FailureKind::Status(404))));
+        assert!(!is_retryable(&policy, &failed(FailureKind::Exit(Some(1)))));
+        assert!(!is_retryable(
+            &policy,
+            &Error::Action("render error".to_string())
+        ));
+    }
+
+    #[test]
+    fn test_is_retryable_configured() {
+        // Setup
+        let policy = RetryPolicy {
+            status: Some(vec![
+                ExpectedStatus::Code(409),
+                ExpectedStatus::Range("502-504".to_string()),
+            ]),
+            errors: Some(vec![RetryableError::Exit]),
+            ..Default::default()
+        };
+
+        // Execute & Verify
+        assert!(is_retryable(&policy, &failed(FailureKind::Status(409))));
+        assert!(is_retryable(&policy, &failed(FailureKind::Status(503))));
+        assert!(!is_retryable(&policy, &failed(FailureKind::Status(500))));
+        assert!(is_retryable(&policy, &failed(FailureKind::Exit(Some(1)))));
+        assert!(!is_retryable(&policy, &failed(FailureKind::Timeout)));
+        assert!(!is_retryable(&policy, &failed(FailureKind::Connect)));
+    }
+
+    #[test]
+    fn test_backoff_grows_exponentially_up_to_max() {
+        // Setup
+        let policy = RetryPolicy {
+            initial_backoff_ms: Some(100),
+            max_backoff_ms: Some(1000),
+            multiplier: Some(3.0),
+            jitter: Some(0.0),
+            ..Default::default()
+        };
+
+        // Execute & Verify
+        assert_eq!(backoff(&policy, 1), Duration::from_millis(100));
+        assert_eq!(backoff(&policy, 2), Duration::from_millis(300));
+        assert_eq!(backoff(&policy, 3), Duration::from_millis(900));
+        assert_eq!(backoff(&policy, 4), Duration::from_millis(1000));
+        assert_eq!(backoff(&policy, 100), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_backoff_jitter_stays_in_bounds() {
+        // Setup
+        let policy = RetryPolicy {
+            initial_backoff_ms: Some(1000),
+            jitter: Some(0.5),
+            ..Default::default()
+        };
+
+        // Execute & Verify
+        for _ in 0..20 {
+            let delay = backoff(&policy, 1);
+            assert!(delay >= Duration::from_millis(500), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(1500), "{:?}", delay);
+        }
+    }
+}
@@ -0,0 +1,503 @@
+use super::{http::truncate, ActionResult};
+use crate::app::{config::rules::ShellAction, template, Error, FailureKind};
+use std::env;
+use std::process::Stdio;
+use std::time::Duration;
+use tera::Context;
+use tokio::process::Command;
+use tracing::{debug, info};
+
+/// Shell used when the action does not configure one
+const DEFAULT_SHELL: &str = "sh";
+
+/// Timeout used when the action does not configure one
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// Maximum length of the stdout and stderr kept in results and errors
+const MAX_OUTPUT_LEN: usize = 1024;
+
+/// Render and run a shell action, either as `<shell> -c <command>` or, with
+/// `argv`, as the program directly
+pub async fn exec_shell_action(
+    action: &ShellAction,
+    context: &Context,
+    dry_run: bool,
+) -> Result<ActionResult, Error> {
+    // Render templates in the program arguments, working dir and environment
+    let argv = render_argv(action, context)?;
+    let command = argv.join(" ");
+
+    let working_dir =
+        match &action.working_dir {
+            Some(working_dir) => Some(template::render_template(working_dir, context).map_err(
+                |e| Error::Action(format!("Failed to render working_dir template: {}", e)),
+            )?),
+            None => None,
+        };
+
+    let environment = action
+        .environment
+        .as_ref()
+        .map(|environment| template::render_template_map(environment, context))
+        .unwrap_or_default();
+
+    if dry_run {
+        // environment values may hold credentials, only their names are logged
+        let mut variables: Vec<&String> = environment.keys().collect();
+        variables.sort();
+        info!(
+            "Dry run shell action: {:?} working_dir={:?} environment={:?}",
+            argv, working_dir, variables
+        );
+        return Ok(ActionResult::dry_run("shell"));
+    }
+
+    let mut process = Command::new(&argv[0]);
+    process
+        .args(&argv[1..])
+        // start from an empty environment with only the allowed server variables
+        .env_clear()
+        .envs(
+            action
+                .env_allowlist
+                .iter()
+                .flatten()
+                .filter_map(|name| env::var(name).ok().map(|value| (name, value))),
+        )
+        .envs(&environment)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // the child is killed if the timeout drops the future
+        .kill_on_drop(true);
+    if let Some(working_dir) = &working_dir {
+        process.current_dir(working_dir);
+    }
+    harden(&mut process, action)?;
+
+    let timeout = Duration::from_secs(action.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    debug!("Shell action: {:?}", argv);
+
+    let child = process
+        .spawn()
+        .map_err(|e| Error::Action(format!("Failed to run shell command {}: {}", command, e)))?;
+    let pid = child.id();
+
+    let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(output) => output.map_err(|e| {
+            Error::Action(format!("Failed to run shell command {}: {}", command, e))
+        })?,
+        Err(_) => {
+            kill_process_group(pid);
+            return Err(Error::ActionFailed(
+                FailureKind::Timeout,
+                format!(
+                    "Shell command timed out after {}s: {}",
+                    timeout.as_secs(),
+                    command
+                ),
+            ));
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    debug!("Shell action stdout: {}", stdout);
+    debug!("Shell action stderr: {}", stderr);
+
+    if !output.status.success() {
+        return Err(Error::ActionFailed(
+            FailureKind::Exit(output.status.code()),
+            format!(
+                "Shell command failed with {}: {}: {}",
+                output.status,
+                command,
+                truncate(stderr.trim().to_string(), MAX_OUTPUT_LEN)
+            ),
+        ));
+    }
+
+    debug!("Shell action status: {}", output.status);
+
+    Ok(ActionResult {
+        action: "shell".to_string(),
+        exit_code: output.status.code(),
+        stdout: Some(truncate(stdout, MAX_OUTPUT_LEN)),
+        stderr: Some(truncate(stderr, MAX_OUTPUT_LEN)),
+        ..Default::default()
+    })
+}
+
+/// Render the program and its arguments
+fn render_argv(action: &ShellAction, context: &Context) -> Result<Vec<String>, Error> {
+    let render = |value: &str, name: &str| {
+        template::render_template(value, context)
+            .map_err(|e| Error::Action(format!("Failed to render {} template: {}", name, e)))
+    };
+
+    match &action.argv {
+        Some(_) if !action.command.is_empty() => Err(Error::Action(
+            "Shell action must set only one of command or argv".to_string(),
+        )),
+        Some(argv) if argv.is_empty() => {
+            Err(Error::Action("Shell action argv is empty".to_string()))
+        }
+        Some(argv) => argv.iter().map(|arg| render(arg, "argv")).collect(),
+        None if action.command.trim().is_empty() => {
+            Err(Error::Action("Shell action command is empty".to_string()))
+        }
+        None => Ok(vec![
+            action.shell.as_deref().unwrap_or(DEFAULT_SHELL).to_string(),
+            "-c".to_string(),
+            render(&action.command, "command")?,
+        ]),
+    }
+}
+
+/// Apply the run-as user, resource limits and a dedicated process group
+#[cfg(unix)]
+fn harden(process: &mut Command, action: &ShellAction) -> Result<(), Error> {
+    // a process group of its own, so a timeout can kill the command's children too
+    process.process_group(0);
+
+    if let Some(gid) = action.gid {
+        process.gid(gid);
+    }
+    if let Some(uid) = action.uid {
+        process.uid(uid);
+    }
+
+    if let Some(limits) = action.limits.clone() {
+        // SAFETY: the closure runs in the forked child before exec and only calls
+        // setrlimit, which is async-signal-safe
+        unsafe {
+            process.pre_exec(move || set_limits(&limits));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn harden(_process: &mut Command, action: &ShellAction) -> Result<(), Error> {
+    if action.uid.is_some() || action.gid.is_some() || action.limits.is_some() {
+        return Err(Error::Action(
+            "Shell action uid, gid and limits are only supported on unix".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_limits(limits: &crate::app::config::rules::ShellLimits) -> std::io::Result<()> {
+    let limit = |value: u64| libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+
+    // SAFETY: setrlimit only reads the given rlimit struct
+    unsafe {
+        if let Some(cpu_seconds) = limits.cpu_seconds {
+            if libc::setrlimit(libc::RLIMIT_CPU, &limit(cpu_seconds)) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        if let Some(memory_mb) = limits.memory_mb {
+            // oversized limits are rejected when the rules are loaded
+            let Some(memory) = memory_mb.checked_mul(1024 * 1024) else {
+                return Err(std::io::Error::from_raw_os_error(libc::EINVAL));
+            };
+            if libc::setrlimit(libc::RLIMIT_AS, &limit(memory)) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        if let Some(open_files) = limits.open_files {
+            if libc::setrlimit(libc::RLIMIT_NOFILE, &limit(open_files)) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Kill the process group led by the command, including anything it started
+#[cfg(unix)]
+fn kill_process_group(pid: Option<u32>) {
+    if let Some(pid) = pid {
+        // SAFETY: kill has no memory safety requirements. The group id is the
+        // command's pid since it was started with process_group(0)
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_pid: Option<u32>) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::rules::ShellLimits;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    fn create_test_shell_action(command: &str) -> ShellAction {
+        ShellAction {
+            command: command.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn create_test_context() -> Context {
+        let mut context = Context::new();
+        context.insert("branch", "main");
+        context
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_captures_output() {
+        let action = create_test_shell_action("echo built {{ branch }}; echo warning >&2");
+
+        let result = exec_shell_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(result.action, "shell");
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(result.stdout.as_deref(), Some("built main\n"));
+        assert_eq!(result.stderr.as_deref(), Some("warning\n"));
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_truncates_output() {
+        let action = create_test_shell_action("head -c 5000 /dev/zero | tr '\\0' x");
+
+        let result = exec_shell_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+
+        let stdout = result.stdout.unwrap();
+        assert_eq!(stdout.len(), MAX_OUTPUT_LEN + "...".len());
+        assert!(stdout.ends_with("..."));
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_environment_and_working_dir() {
+        let dir = tempdir().unwrap();
+        let action = ShellAction {
+            command: "echo $BRANCH; pwd".to_string(),
+            working_dir: Some(dir.path().display().to_string()),
+            environment: Some(HashMap::from([(
+                "BRANCH".to_string(),
+                "{{ branch }}".to_string(),
+            )])),
+            ..Default::default()
+        };
+
+        let result = exec_shell_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+
+        let stdout = result.stdout.unwrap();
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines[0], "main");
+        assert_eq!(
+            std::fs::canonicalize(lines[1]).unwrap(),
+            std::fs::canonicalize(dir.path()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_non_zero_exit() {
+        let action = create_test_shell_action("echo broken >&2; exit 3");
+
+        let result = exec_shell_action(&action, &create_test_context(), false).await;
+
+        match result {
+            Err(Error::ActionFailed(FailureKind::Exit(Some(3)), message)) => {
+                assert!(message.contains("exit status: 3"), "{}", message);
+                assert!(message.contains("broken"), "{}", message);
+            }
+            other => panic!("Expected action error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_timeout() {
+        let action = ShellAction {
+            command: "sleep 5".to_string(),
+            timeout: Some(1),
+            ..Default::default()
+        };
+
+        let result = exec_shell_action(&action, &create_test_context(), false).await;
+
+        match result {
+            Err(Error::ActionFailed(FailureKind::Timeout, message)) => {
+                assert!(message.contains("timed out"), "{}", message)
+            }
+            other => panic!("Expected action error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_custom_shell() {
+        let action = ShellAction {
+            command: "echo ${BASH_VERSION:+bash}".to_string(),
+            shell: Some("bash".to_string()),
+            ..Default::default()
+        };
+
+        let result = exec_shell_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(result.stdout.as_deref(), Some("bash\n"));
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_dry_run_does_not_run() {
+        let dir = tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        let action = create_test_shell_action(&format!("touch {}", marker.display()));
+
+        let result = exec_shell_action(&action, &create_test_context(), true)
+            .await
+            .unwrap();
+
+        assert!(result.dry_run);
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_starts_from_empty_environment() {
+        env::set_var("GIT_ACTIONS_TEST_SECRET", "secret");
+        env::set_var("GIT_ACTIONS_TEST_ALLOWED", "allowed");
+        let action = ShellAction {
+            command: "echo ${GIT_ACTIONS_TEST_SECRET:-unset} ${GIT_ACTIONS_TEST_ALLOWED:-unset}"
+                .to_string(),
+            env_allowlist: Some(vec!["GIT_ACTIONS_TEST_ALLOWED".to_string()]),
+            ..Default::default()
+        };
+
+        let result = exec_shell_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(result.stdout.as_deref(), Some("unset allowed\n"));
+        env::remove_var("GIT_ACTIONS_TEST_SECRET");
+        env::remove_var("GIT_ACTIONS_TEST_ALLOWED");
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_argv_avoids_shell_interpolation() {
+        let mut context = Context::new();
+        context.insert("branch", "main; echo injected");
+        let action = ShellAction {
+            argv: Some(vec![
+                "printf".to_string(),
+                "%s".to_string(),
+                "{{ branch }}".to_string(),
+            ]),
+            ..Default::default()
+        };
+
+        let result = exec_shell_action(&action, &context, false).await.unwrap();
+
+        assert_eq!(result.stdout.as_deref(), Some("main; echo injected"));
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_command_and_argv_rejected() {
+        let action = ShellAction {
+            command: "echo".to_string(),
+            argv: Some(vec!["echo".to_string()]),
+            ..Default::default()
+        };
+
+        let result = exec_shell_action(&action, &create_test_context(), false).await;
+
+        assert!(matches!(result, Err(Error::Action(_))));
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_empty_command_rejected() {
+        let action = ShellAction::default();
+
+        let result = exec_shell_action(&action, &create_test_context(), false).await;
+
+        assert!(matches!(result, Err(Error::Action(_))));
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_limits() {
+        let action = ShellAction {
+            command: "ulimit -t; ulimit -n; ulimit -v".to_string(),
+            limits: Some(ShellLimits {
+                cpu_seconds: Some(10),
+                memory_mb: Some(512),
+                open_files: Some(64),
+            }),
+            ..Default::default()
+        };
+
+        let result = exec_shell_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(result.stdout.as_deref(), Some("10\n64\n524288\n"));
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_timeout_kills_process_group() {
+        let dir = tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let action = ShellAction {
+            command: format!("sleep 30 & echo $! > {}; wait", pid_file.display()),
+            timeout: Some(1),
+            ..Default::default()
+        };
+
+        let result = exec_shell_action(&action, &create_test_context(), false).await;
+        assert!(result.is_err());
+
+        // the background sleep is gone or a zombie waiting to be reaped
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        for _ in 0..20 {
+            match std::fs::read_to_string(format!("/proc/{}/stat", pid.trim())) {
+                Ok(stat) if !stat.contains(") Z ") => {
+                    tokio::time::sleep(Duration::from_millis(50)).await
+                }
+                _ => return,
+            }
+        }
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid.trim()));
+        assert!(
+            stat.map(|s| s.contains(") Z ")).unwrap_or(true),
+            "background process still running"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell_action_run_as_user() {
+        // dropping privileges needs root
+        if unsafe { libc::geteuid() } != 0 {
+            return;
+        }
+        let action = ShellAction {
+            command: "id -u; id -g".to_string(),
+            uid: Some(65534),
+            gid: Some(65534),
+            ..Default::default()
+        };
+
+        let result = exec_shell_action(&action, &create_test_context(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(result.stdout.as_deref(), Some("65534\n65534\n"));
+    }
+}
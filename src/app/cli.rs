@@ -0,0 +1,78 @@
+use crate::app::{
+    config::{Config, ServerConfig},
+    logging,
+    worker::{dead_letter::DeadLetterStore, exec_job},
+};
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+
+/// Dead letter commands, run offline against the server's dead letter directory
+#[derive(Subcommand, Debug)]
+pub enum DeadLetterCommand {
+    /// List the dead letters, oldest first
+    List,
+    /// Print a dead letter with its rendered request, event and error
+    Show { id: String },
+    /// Run the actions of a dead letter's rule again and remove the dead letter
+    Replay { id: String },
+    /// Remove a dead letter
+    Discard { id: String },
+}
+
+/// Run a dead letter command
+pub async fn dead_letters(server_config: ServerConfig, command: DeadLetterCommand) -> Result<()> {
+    let Some(dir) = server_config
+        .spec
+        .execution
+        .as_ref()
+        .and_then(|execution| execution.dead_letter_dir.as_deref())
+    else {
+        bail!("Dead letters are not enabled: execution.dead_letter_dir is not set");
+    };
+    let store = DeadLetterStore::open(dir)?;
+
+    match command {
+        DeadLetterCommand::List => {
+            for dead_letter in store.list().map_err(to_anyhow)? {
+                println!(
+                    "{}  {}  {}  {}",
+                    dead_letter.id,
+                    dead_letter.failed_at.to_rfc3339(),
+                    dead_letter.rule_name,
+                    dead_letter.error
+                );
+            }
+        }
+        DeadLetterCommand::Show { id } => {
+            let dead_letter = store.get(&id).map_err(to_anyhow)?;
+            println!("{}", serde_json::to_string_pretty(&dead_letter)?);
+        }
+        DeadLetterCommand::Replay { id } => {
+            logging::setup(&server_config.spec.logging)
+                .with_context(|| "Failed to setup logging")?;
+
+            // the chat webhook URLs of the job are restored from its configured rule
+            let mut config = Config::new();
+            config.load(&server_config.spec.configs)?;
+            let mut job = store.get(&id).map_err(to_anyhow)?.job;
+            job.restore(&config);
+
+            // a replay that fails again replaces the dead letter
+            exec_job(&job, Some(&store)).await.map_err(|e| {
+                anyhow::anyhow!("Replay failed, dead letter {} kept: {}", id, e.error)
+            })?;
+            store.remove(&id).map_err(to_anyhow)?;
+            println!("Replayed dead letter {}", id);
+        }
+        DeadLetterCommand::Discard { id } => {
+            store.remove(&id).map_err(to_anyhow)?;
+            println!("Discarded dead letter {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+fn to_anyhow(e: crate::app::Error) -> anyhow::Error {
+    anyhow::anyhow!("{:?}", e)
+}
@@ -0,0 +1,135 @@
+use anyhow::{bail, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use super::rules::{validate_timezone, TimeWindow};
+use super::{ApiVersion, Metadata};
+use crate::app::config::types::ConfigKind;
+
+/// Freeze calendar configuration, referenced by name from rule schedules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreezeCalendarConfig {
+    #[serde(rename = "apiVersion")]
+    pub api_version: ApiVersion,
+    pub kind: ConfigKind,
+    pub metadata: Metadata,
+    pub spec: FreezeCalendarSpec,
+}
+
+/// Freeze calendar specification
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FreezeCalendarSpec {
+    /// IANA timezone of the periods and windows, e.g. "Europe/London". Defaults to UTC
+    pub timezone: Option<String>,
+
+    /// One-off freeze periods, e.g. holidays
+    pub periods: Option<Vec<FreezePeriod>>,
+
+    /// Recurring weekly freezes, e.g. Friday evenings
+    pub windows: Option<Vec<TimeWindow>>,
+}
+
+impl FreezeCalendarSpec {
+    /// Check the timezone, the dates of the periods and the times of the windows
+    pub fn validate(&self) -> Result<()> {
+        validate_timezone(&self.timezone)?;
+        for period in self.periods.iter().flatten() {
+            for date in [&period.start, &period.end] {
+                let valid = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M").is_ok()
+                    || NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok();
+                if !valid {
+                    bail!(
+                        "Invalid date {}, expected YYYY-MM-DD or YYYY-MM-DDTHH:MM",
+                        date
+                    );
+                }
+            }
+        }
+        for window in self.windows.iter().flatten() {
+            window.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// One-off freeze period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FreezePeriod {
+    /// Start "YYYY-MM-DD" or "YYYY-MM-DDTHH:MM", inclusive
+    pub start: String,
+
+    /// End "YYYY-MM-DD" or "YYYY-MM-DDTHH:MM", exclusive. A date ends at the end of that day
+    pub end: String,
+
+    /// Reason for the freeze, used in logs
+    pub reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Weekday;
+    use serde_yaml;
+
+    #[test]
+    fn test_deserialize_freeze_calendar_config() {
+        let yaml = r#"
+apiVersion: v1
+kind: FreezeCalendar
+metadata:
+  name: holidays
+spec:
+  timezone: "Europe/London"
+  periods:
+    - start: "2025-12-24"
+      end: "2025-12-26"
+      reason: "Christmas"
+  windows:
+    - days: ["fri"]
+      start: "17:00"
+"#;
+
+        let config: FreezeCalendarConfig = serde_yaml::from_str(yaml).unwrap();
+
+        // Verify the config
+        assert!(matches!(config.kind, ConfigKind::FreezeCalendar));
+        assert_eq!(config.metadata.name, "holidays");
+        assert_eq!(config.spec.timezone, Some("Europe/London".to_string()));
+        assert_eq!(
+            config.spec.periods,
+            Some(vec![FreezePeriod {
+                start: "2025-12-24".to_string(),
+                end: "2025-12-26".to_string(),
+                reason: Some("Christmas".to_string()),
+            }])
+        );
+        assert_eq!(
+            config.spec.windows,
+            Some(vec![TimeWindow {
+                days: Some(vec![Weekday::Fri]),
+                start: Some("17:00".to_string()),
+                end: None,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_validate_freeze_calendar() {
+        let calendar = |timezone: &str, start: &str| FreezeCalendarSpec {
+            timezone: Some(timezone.to_string()),
+            periods: Some(vec![FreezePeriod {
+                start: start.to_string(),
+                end: "2025-12-26".to_string(),
+                reason: None,
+            }]),
+            windows: None,
+        };
+
+        assert!(calendar("Europe/London", "2025-12-24").validate().is_ok());
+        assert!(calendar("Europe/London", "2025-12-24T08:00")
+            .validate()
+            .is_ok());
+        assert!(calendar("Not/A_Zone", "2025-12-24").validate().is_err());
+        assert!(calendar("Europe/London", "24/12/2025").validate().is_err());
+    }
+}
@@ -1,7 +1,9 @@
+pub mod calendar;
 pub mod rules;
 pub mod server;
 pub mod webhook;
 
+pub use calendar::FreezeCalendarConfig;
 pub use rules::{Action, Rule, RulesConfig};
 pub use server::ServerConfig;
 pub use types::*;
@@ -1,8 +1,17 @@
+use anyhow::{anyhow, bail, Context as _, Result};
+use chrono::{NaiveTime, Weekday};
+use chrono_tz::Tz;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::RangeInclusive;
 
 use crate::app::config::types::{ApiVersion, ConfigKind, Metadata};
 
+/// Actions run at the same time by a rule whose actions have `needs`
+pub const DEFAULT_MAX_PARALLEL: usize = 4;
+
 /// Rules configuration for Git-Actions
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RulesConfig {
@@ -23,12 +32,12 @@ pub struct RulesConfig {
 /// Rules specification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RulesSpec {
-    /// Rules configurations - hashmap with rule name as key
-    pub rules: HashMap<String, Rule>,
+    /// Rules configurations - map with rule name as key, in declaration order
+    pub rules: IndexMap<String, Rule>,
 }
 
 /// Rule configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Rule {
     /// Description of the rule
     pub description: Option<String>,
@@ -46,12 +55,212 @@ pub struct Rule {
     /// Path filters to match
     pub paths: Option<Vec<PathFilter>>,
 
+    /// Pull request title, description and author filters to match
+    pub pull_request: Option<PullRequestFilter>,
+
+    /// Monorepo project names to match, wildcards allowed, e.g. "services/*"
+    pub projects: Option<Vec<String>>,
+
+    /// Time windows and freeze calendars controlling when the actions may run
+    pub schedule: Option<Schedule>,
+
+    /// Whether the rule is evaluated at all. Defaults to true
+    pub enabled: Option<bool>,
+
+    /// Evaluate the rule and render its actions, logging them instead of running them
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Evaluation priority, higher runs first. Rules with the same priority
+    /// run in declaration order
+    #[serde(default)]
+    pub priority: i32,
+
+    /// Stop evaluating lower priority rules when this rule matches
+    #[serde(default)]
+    pub stop: bool,
+
+    /// Post a commit build status keyed by the rule name through the webhook's
+    /// provider API: in progress when the actions start, then successful or failed
+    #[serde(default)]
+    pub report_status: bool,
+
     /// Actions to perform when the rule matches
     pub actions: Vec<Action>,
+
+    /// Template rendering a list, the rule's actions run once per item with the
+    /// item as `item`, see `template::render_items`
+    pub foreach: Option<String>,
+
+    /// Number of actions, and items of actions with a `foreach`, run at the same
+    /// time, each action once the actions it needs finished. See `max_parallel()`
+    /// for the default
+    pub max_parallel: Option<usize>,
+
+    /// Actions to perform after the actions succeeded
+    #[serde(default)]
+    pub on_success: Vec<Action>,
+
+    /// Actions to perform after an action failed, with the failure as `failure`
+    #[serde(default)]
+    pub on_failure: Vec<Action>,
+
+    /// Actions to perform after the actions and their hooks, whether they failed or not
+    #[serde(default)]
+    pub always: Vec<Action>,
+}
+
+impl Rule {
+    /// Whether the rule is enabled, rules are enabled unless `enabled: false` is set
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Number of actions run at the same time. Defaults to `DEFAULT_MAX_PARALLEL`
+    /// when an action has `needs`, so that independent actions run concurrently,
+    /// and to 1 otherwise, running the actions one at a time in declaration order
+    pub fn max_parallel(&self) -> usize {
+        let uses_needs = [
+            &self.actions,
+            &self.on_success,
+            &self.on_failure,
+            &self.always,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|action| action.needs.as_ref().is_some_and(|needs| !needs.is_empty()));
+
+        match self.max_parallel {
+            Some(max_parallel) => max_parallel,
+            None if uses_needs => DEFAULT_MAX_PARALLEL,
+            None => 1,
+        }
+    }
+
+    /// Check the actions and hooks: step ids are unique, Bitbucket actions have
+    /// an operation, multipart file paths are not templates, and `needs` refer to actions of the same list without forming a cycle.
+    /// Check the schedule's timezone and windows
+    pub fn validate(&self) -> Result<()> {
+        if let Some(schedule) = &self.schedule {
+            schedule.validate().with_context(|| "Invalid schedule")?;
+        }
+
+        let mut ids = HashSet::new();
+        for action in [
+            &self.actions,
+            &self.on_success,
+            &self.on_failure,
+            &self.always,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if let Some(id) = &action.id {
+                if !ids.insert(id) {
+                    bail!("Duplicate action id: {}", id);
+                }
+            }
+            if action
+                .bitbucket
+                .as_ref()
+                .is_some_and(BitbucketAction::is_empty)
+            {
+                bail!("Bitbucket action has no operation");
+            }
+            let statuses = action
+                .http
+                .iter()
+                .flat_map(|http| http.expected_status.iter())
+                .chain(action.retry.iter().flat_map(|retry| retry.status.iter()));
+            for status in statuses.flatten() {
+                status.range()?;
+            }
+            if let Some(limits) = action
+                .shell
+                .as_ref()
+                .and_then(|shell| shell.limits.as_ref())
+            {
+                limits.validate()?;
+            }
+            let multipart = action.http.iter().flat_map(|http| http.multipart.iter());
+            for (name, field) in multipart.flatten() {
+                if let MultipartField::File { file } = field {
+                    if file.contains("{{") || file.contains("{%") {
+                        bail!(
+                            "Multipart file {} path cannot be a template: {}",
+                            name,
+                            file
+                        );
+                    }
+                }
+            }
+        }
+
+        for actions in [
+            &self.actions,
+            &self.on_success,
+            &self.on_failure,
+            &self.always,
+        ] {
+            check_needs(actions)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the files the rule's filters refer to
+    pub fn load_files(&mut self) -> Result<()> {
+        let authors = self
+            .pull_request
+            .iter_mut()
+            .flat_map(|pull_request| pull_request.authors.iter_mut())
+            .flatten();
+        for author in authors {
+            author.load()?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that the actions of a list can all run given their `needs`
+fn check_needs(actions: &[Action]) -> Result<()> {
+    let ids: HashSet<&str> = actions.iter().filter_map(|a| a.id.as_deref()).collect();
+    for action in actions {
+        for need in action.needs.iter().flatten() {
+            if !ids.contains(need.as_str()) {
+                bail!("Action needs an unknown action: {}", need);
+            }
+        }
+    }
+
+    // resolve the actions whose needs are resolved until none is left
+    let mut resolved: HashSet<&str> = HashSet::new();
+    let mut pending: Vec<&Action> = actions.iter().collect();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|action| {
+            let ready = action
+                .needs
+                .iter()
+                .flatten()
+                .all(|need| resolved.contains(need.as_str()));
+            if ready {
+                if let Some(id) = &action.id {
+                    resolved.insert(id);
+                }
+            }
+            !ready
+        });
+        if pending.len() == before {
+            bail!("Action needs form a cycle");
+        }
+    }
+
+    Ok(())
 }
 
 /// Branch filter
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum BranchFilter {
     /// Exact branch name match
@@ -67,7 +276,7 @@ pub enum BranchFilter {
 }
 
 /// Path filter
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum PathFilter {
     /// Exact path match
@@ -82,40 +291,650 @@ pub enum PathFilter {
     // Not { not: String },
 }
 
+/// Pull request filters, all configured filters must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PullRequestFilter {
+    /// Title filters to match
+    pub title: Option<Vec<TextFilter>>,
+
+    /// Description filters to match
+    pub description: Option<Vec<TextFilter>>,
+
+    /// Author filters to match
+    pub authors: Option<Vec<AuthorFilter>>,
+}
+
+/// Schedule of a rule. It does not affect whether the rule matches, only
+/// whether its actions run now
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Schedule {
+    /// IANA timezone of the windows, e.g. "Europe/London". Defaults to UTC
+    pub timezone: Option<String>,
+
+    /// Actions only run inside one of these windows
+    pub allow: Option<Vec<TimeWindow>>,
+
+    /// Actions never run inside these windows
+    pub block: Option<Vec<TimeWindow>>,
+
+    /// Names of freeze calendars during which actions never run
+    pub freezes: Option<Vec<String>>,
+
+    /// Queue blocked actions until the schedule opens instead of dropping them
+    #[serde(default)]
+    pub queue: bool,
+}
+
+impl Schedule {
+    /// Check the timezone and the times of the windows
+    pub fn validate(&self) -> Result<()> {
+        validate_timezone(&self.timezone)?;
+        for window in self.allow.iter().chain(&self.block).flatten() {
+            window.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that a timezone is a known IANA timezone
+pub(crate) fn validate_timezone(timezone: &Option<String>) -> Result<()> {
+    if let Some(timezone) = timezone {
+        if timezone.parse::<Tz>().is_err() {
+            bail!("Invalid timezone: {}", timezone);
+        }
+    }
+    Ok(())
+}
+
+/// Recurring weekly time window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    /// Days of the week the window starts on, e.g. ["mon", "fri"]. Defaults to every day
+    pub days: Option<Vec<Weekday>>,
+
+    /// Start time "HH:MM", inclusive. Defaults to 00:00
+    pub start: Option<String>,
+
+    /// End time "HH:MM", exclusive. Defaults to the end of the day.
+    /// An end before the start wraps past midnight
+    pub end: Option<String>,
+}
+
+impl TimeWindow {
+    /// Check that the start and end are "HH:MM" times
+    pub fn validate(&self) -> Result<()> {
+        for time in self.start.iter().chain(&self.end) {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .with_context(|| format!("Invalid time {}, expected HH:MM", time))?;
+        }
+        Ok(())
+    }
+}
+
+/// Text filter for pull request fields such as the title and description
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TextFilter {
+    /// Exact text match
+    Exact { exact: String },
+
+    /// Wildcard text match
+    Pattern { pattern: String },
+
+    /// Regex text search
+    Regex { regex: String },
+
+    /// Negated filter, the rule fails if the inner filter matches
+    Not { not: Box<TextFilter> },
+}
+
+/// Pull request author filter, usernames and emails are matched case-insensitively
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AuthorFilter {
+    /// Author username match
+    Username { username: String },
+
+    /// Author email match
+    Email { email: String },
+
+    /// Author username or email is one of the list
+    Users { users: Vec<String> },
+
+    /// Author username or email is listed in a file, one per line. Lines
+    /// starting with `#` are comments. The file is read when the config is loaded
+    GroupFile {
+        group_file: String,
+
+        /// Usernames and emails read from the group file
+        #[serde(skip)]
+        members: Vec<String>,
+    },
+}
+
+impl AuthorFilter {
+    /// Read the members of a group file filter
+    pub fn load(&mut self) -> Result<()> {
+        if let AuthorFilter::GroupFile {
+            group_file,
+            members,
+        } = self
+        {
+            let content = fs::read_to_string(&*group_file)
+                .with_context(|| format!("Could not read group file {}", group_file))?;
+            *members = content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+                .collect();
+        }
+        Ok(())
+    }
+}
+
 /// Action configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Action {
+    /// Step id, later actions of the rule use the action's outputs as `steps.<id>.outputs`
+    pub id: Option<String>,
+
     /// HTTP action
     pub http: Option<HttpAction>,
 
     /// Shell action
     pub shell: Option<ShellAction>,
+
+    /// Bitbucket action on the event's pull request
+    pub bitbucket: Option<BitbucketAction>,
+
+    /// Slack message posted to an incoming webhook
+    pub slack: Option<SlackAction>,
+
+    /// Microsoft Teams message posted to an incoming webhook
+    pub teams: Option<TeamsAction>,
+
+    /// Retry policy of the action
+    pub retry: Option<RetryPolicy>,
+
+    /// Run the next actions of the rule even if this action fails
+    #[serde(default)]
+    pub continue_on_error: bool,
+
+    /// Ids of the actions that must finish before this action starts
+    pub needs: Option<Vec<String>>,
+
+    /// Template rendering a list, the action runs once per item with the item
+    /// as `item`, see `template::render_items`
+    pub foreach: Option<String>,
+
+    /// Template expression deciding whether the action runs, e.g.
+    /// `event.branch == "main"`, evaluated when the action is about to start
+    #[serde(rename = "if")]
+    pub condition: Option<String>,
+}
+
+/// Retry policy of an action. Failed attempts are retried after a backoff
+/// growing exponentially from the initial backoff
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts including the first one, defaults to 3
+    pub max_attempts: Option<u32>,
+
+    /// Backoff in milliseconds before the first retry, defaults to 1000
+    pub initial_backoff_ms: Option<u64>,
+
+    /// Upper bound of the backoff in milliseconds, defaults to 60000
+    pub max_backoff_ms: Option<u64>,
+
+    /// Factor applied to the backoff after each retry, defaults to 2
+    pub multiplier: Option<f64>,
+
+    /// Random fraction of the backoff added or removed, from 0 to 1. Defaults to 0.1
+    pub jitter: Option<f64>,
+
+    /// Retryable HTTP statuses, defaults to 429 and any 5xx status
+    pub status: Option<Vec<ExpectedStatus>>,
+
+    /// Retryable failures, defaults to timeout and connect
+    pub errors: Option<Vec<RetryableError>>,
+}
+
+/// Failure retried by a retry policy
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetryableError {
+    /// HTTP request or shell command timeout
+    Timeout,
+
+    /// HTTP connection error
+    Connect,
+
+    /// Shell command non-zero exit status
+    Exit,
 }
 
 /// HTTP action configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct HttpAction {
-    /// HTTP method
+    /// HTTP method: GET, POST, PUT, PATCH, DELETE or HEAD
     pub method: String,
 
     /// URL to call
     pub url: String,
 
+    /// Query parameters appended to the URL
+    pub query: Option<HashMap<String, String>>,
+
     /// HTTP headers
     pub headers: Option<HashMap<String, String>>,
 
+    /// Authentication added as the Authorization header
+    pub auth: Option<HttpAuth>,
+
     /// HTTP body
     pub body: Option<String>,
+
+    /// JSON body, string values are rendered as templates. Sets the JSON content type
+    pub json: Option<serde_json::Value>,
+
+    /// URL encoded form body with templated values. Sets the form content type
+    pub form: Option<HashMap<String, String>>,
+
+    /// Multipart form body with templated text fields and files
+    pub multipart: Option<HashMap<String, MultipartField>>,
+
+    /// Timeout in seconds, defaults to 30
+    pub timeout: Option<u64>,
+
+    /// Maximum number of redirects followed, 0 disables redirects. Defaults to 10
+    pub max_redirects: Option<usize>,
+
+    /// Response statuses treated as success, defaults to any 2xx status
+    pub expected_status: Option<Vec<ExpectedStatus>>,
+
+    /// Values extracted from the response by output name
+    pub outputs: Option<HashMap<String, OutputExtractor>>,
+
+    /// Accept invalid TLS certificates and host names. For lab environments only
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+/// Expected HTTP response status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ExpectedStatus {
+    /// Exact status code, e.g. 204
+    Code(u16),
+
+    /// Status range such as "200-299", or a status class such as "2xx"
+    Range(String),
+}
+
+impl ExpectedStatus {
+    /// Statuses matched: a single code, a range such as "200-299", or a status
+    /// class such as "2xx"
+    pub fn range(&self) -> Result<RangeInclusive<u16>> {
+        let range = match self {
+            ExpectedStatus::Code(code) => return Ok(*code..=*code),
+            ExpectedStatus::Range(range) => range.trim(),
+        };
+        let invalid = || anyhow!("Invalid expected status: {}", range);
+
+        if let Some(class) = range.strip_suffix("xx").or(range.strip_suffix("XX")) {
+            let class: u16 = class.parse().map_err(|_| invalid())?;
+            if !(1..=5).contains(&class) {
+                return Err(invalid());
+            }
+            return Ok(class * 100..=class * 100 + 99);
+        }
+        if let Some((start, end)) = range.split_once('-') {
+            let start: u16 = start.trim().parse().map_err(|_| invalid())?;
+            let end: u16 = end.trim().parse().map_err(|_| invalid())?;
+            if start > end {
+                return Err(invalid());
+            }
+            return Ok(start..=end);
+        }
+        let code = range.parse().map_err(|_| invalid())?;
+        Ok(code..=code)
+    }
+}
+
+/// Extraction of an HTTP action output from the response. The body is used
+/// unless a header is set; a JSON pointer and then a regex are applied to it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutputExtractor {
+    /// Response header to extract from instead of the body
+    pub header: Option<String>,
+
+    /// JSON pointer into the value, e.g. "/deployment/id"
+    pub json: Option<String>,
+
+    /// Regex searched in the value, the first capture group or else the whole match is used
+    pub regex: Option<String>,
+}
+
+/// Multipart form field of an HTTP action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MultipartField {
+    /// Text field
+    Text(String),
+
+    /// File uploaded from a path. The path is not templated
+    File { file: String },
+}
+
+/// HTTP action authentication
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HttpAuth {
+    /// Basic authentication
+    Basic { basic: BasicAuth },
+
+    /// Bearer token authentication
+    Bearer { bearer: String },
+}
+
+/// HTTP basic authentication credentials
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BasicAuth {
+    /// User name
+    pub username: String,
+
+    /// Password
+    pub password: Option<String>,
 }
 
 /// Shell action configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ShellAction {
     /// Command to execute
+    #[serde(default)]
     pub command: String,
 
+    /// Program and arguments run directly without a shell, e.g. ["make", "build"].
+    /// Each argument is templated on its own, so event values cannot inject shell syntax.
+    /// Used instead of `command`
+    pub argv: Option<Vec<String>>,
+
     /// Working directory
     pub working_dir: Option<String>,
+
+    /// Shell running the command as `<shell> -c <command>`, defaults to "sh"
+    pub shell: Option<String>,
+
+    /// Environment variables added to the command's environment
+    pub environment: Option<HashMap<String, String>>,
+
+    /// Server environment variables passed through to the command. The command
+    /// otherwise starts from an empty environment
+    pub env_allowlist: Option<Vec<String>>,
+
+    /// User id to run the command as
+    pub uid: Option<u32>,
+
+    /// Group id to run the command as
+    pub gid: Option<u32>,
+
+    /// Resource limits of the command
+    pub limits: Option<ShellLimits>,
+
+    /// Timeout in seconds, defaults to 600. The command's whole process group is
+    /// killed when it expires
+    pub timeout: Option<u64>,
+}
+
+/// Bitbucket action configuration. It calls the Bitbucket API of the webhook
+/// the event came from, with the webhook's credentials, on the event's pull
+/// request. The operations set run in the order below
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BitbucketAction {
+    /// Comment posted on the pull request
+    pub comment: Option<BitbucketComment>,
+
+    /// Build status set on a commit
+    pub build_status: Option<BitbucketBuildStatus>,
+
+    /// Code Insights report, with its annotations, set on a commit
+    pub insights: Option<BitbucketInsights>,
+
+    /// Usernames added to the pull request's reviewers
+    pub reviewers: Option<Vec<String>>,
+
+    /// Approve, decline or merge the pull request
+    pub pull_request: Option<PullRequestOperation>,
+}
+
+impl BitbucketAction {
+    /// Whether no operation is set
+    pub fn is_empty(&self) -> bool {
+        self.comment.is_none()
+            && self.build_status.is_none()
+            && self.insights.is_none()
+            && self.reviewers.is_none()
+            && self.pull_request.is_none()
+    }
+}
+
+/// Pull request comment of a Bitbucket action
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BitbucketComment {
+    /// Markdown text of the comment
+    pub text: String,
+
+    /// Key of the comment. A comment with a key is updated by later runs with
+    /// the same key instead of posting a new comment
+    pub key: Option<String>,
+}
+
+/// Commit build status of a Bitbucket action
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BitbucketBuildStatus {
+    /// State of the build
+    pub state: BuildState,
+
+    /// Key identifying the build, a later status with the same key replaces it
+    pub key: String,
+
+    /// Display name of the build
+    pub name: Option<String>,
+
+    /// Link to the build
+    pub url: String,
+
+    /// Description of the build
+    pub description: Option<String>,
+
+    /// Commit hash, defaults to the pull request's latest commit
+    pub commit: Option<String>,
+}
+
+/// State of a commit build status
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum BuildState {
+    #[default]
+    Inprogress,
+    Successful,
+    Failed,
+}
+
+/// Code Insights report of a Bitbucket action. A report with the same key
+/// replaces the commit's previous report and annotations
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BitbucketInsights {
+    /// Key identifying the report
+    pub key: String,
+
+    /// Title of the report
+    pub title: String,
+
+    /// Result of the report, rendering to PASS or FAIL
+    pub result: Option<String>,
+
+    /// Details of the report
+    pub details: Option<String>,
+
+    /// Name of the tool reporting
+    pub reporter: Option<String>,
+
+    /// Link to the full report
+    pub link: Option<String>,
+
+    /// Data shown in the report, string values are rendered as templates
+    pub data: Option<Vec<InsightsData>>,
+
+    /// Annotations of the report
+    pub annotations: Option<InsightsAnnotations>,
+
+    /// Commit hash, defaults to the pull request's latest commit
+    pub commit: Option<String>,
+}
+
+/// Data field of a Code Insights report
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InsightsData {
+    /// Title of the field
+    pub title: String,
+
+    /// Type of the value: BOOLEAN, DATE, DURATION, LINK, NUMBER, PERCENTAGE or TEXT
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+
+    /// Value of the field
+    pub value: serde_json::Value,
+}
+
+/// Annotations of a Code Insights report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InsightsAnnotations {
+    /// Annotations whose string fields are rendered as templates
+    List(Vec<InsightsAnnotation>),
+
+    /// Template rendering a JSON array of annotations, e.g. a previous step's output
+    Template(String),
+}
+
+/// Code Insights annotation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsightsAnnotation {
+    /// Message of the annotation
+    pub message: String,
+
+    /// Severity of the annotation
+    pub severity: AnnotationSeverity,
+
+    /// Repository path of the annotated file, the annotation is on the report otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Annotated line, the annotation is on the whole file otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+
+    /// Type of the annotation
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub annotation_type: Option<AnnotationType>,
+
+    /// Link to details of the annotation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+
+    /// Id of the annotation in the reporting tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+}
+
+/// Severity of a Code Insights annotation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum AnnotationSeverity {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+/// Type of a Code Insights annotation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AnnotationType {
+    Bug,
+    CodeSmell,
+    Vulnerability,
+}
+
+/// Operation changing the state of a pull request
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestOperation {
+    Approve,
+    Decline,
+    Merge,
+}
+
+/// Slack action configuration. Without text and blocks, the message summarizes
+/// the pull request: title, author, branch, matched rule and link
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SlackAction {
+    /// Incoming webhook URL, e.g. "{{ env.SLACK_WEBHOOK_URL }}"
+    pub webhook_url: String,
+
+    /// Message text, the notification text when blocks are set
+    pub text: Option<String>,
+
+    /// Block Kit blocks, string values are rendered as templates
+    pub blocks: Option<serde_json::Value>,
+
+    /// Timeout in seconds, defaults to 30
+    pub timeout: Option<u64>,
+}
+
+/// Microsoft Teams action configuration. Without text and card, the message is
+/// an Adaptive Card summarizing the pull request like the Slack default
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TeamsAction {
+    /// Incoming webhook URL, e.g. "{{ env.TEAMS_WEBHOOK_URL }}"
+    pub webhook_url: String,
+
+    /// Message text, shown in a card linking to the pull request
+    pub text: Option<String>,
+
+    /// Adaptive Card, string values are rendered as templates
+    pub card: Option<serde_json::Value>,
+
+    /// Timeout in seconds, defaults to 30
+    pub timeout: Option<u64>,
+}
+
+/// Resource limits of a shell action
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShellLimits {
+    /// CPU time in seconds
+    pub cpu_seconds: Option<u64>,
+
+    /// Address space in megabytes
+    pub memory_mb: Option<u64>,
+
+    /// Number of open files
+    pub open_files: Option<u64>,
+}
+
+impl ShellLimits {
+    /// Check that the memory limit fits in bytes
+    pub fn validate(&self) -> Result<()> {
+        if let Some(memory_mb) = self.memory_mb {
+            if memory_mb.checked_mul(1024 * 1024).is_none() {
+                bail!("Memory limit is too large: {} MB", memory_mb);
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -123,12 +942,209 @@ mod tests {
     use super::*;
     use serde_yaml;
 
+    fn create_test_action(id: &str, needs: &[&str]) -> Action {
+        Action {
+            id: Some(id.to_string()),
+            needs: Some(needs.iter().map(|need| need.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_rule_needs() {
+        let valid = Rule {
+            actions: vec![
+                create_test_action("report", &["a", "b"]),
+                create_test_action("a", &[]),
+                create_test_action("b", &["a"]),
+            ],
+            ..Default::default()
+        };
+        let unknown = Rule {
+            actions: vec![create_test_action("a", &["missing"])],
+            ..Default::default()
+        };
+        let cycle = Rule {
+            actions: vec![
+                create_test_action("a", &["b"]),
+                create_test_action("b", &["a"]),
+            ],
+            ..Default::default()
+        };
+        let duplicate = Rule {
+            actions: vec![create_test_action("a", &[])],
+            always: vec![create_test_action("a", &[])],
+            ..Default::default()
+        };
+
+        assert!(valid.validate().is_ok());
+        assert!(unknown.validate().is_err());
+        assert!(cycle.validate().is_err());
+        assert!(duplicate.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_rule_multipart_file() {
+        let upload = |file: &str| Rule {
+            actions: vec![Action {
+                http: Some(HttpAction {
+                    multipart: Some(
+                        [(
+                            "report".to_string(),
+                            MultipartField::File {
+                                file: file.to_string(),
+                            },
+                        )]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert!(upload("target/report.xml").validate().is_ok());
+        assert!(upload("{{ event.branch }}/report.xml").validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_rule_statuses() {
+        let http = |status: &str| Rule {
+            actions: vec![Action {
+                http: Some(HttpAction {
+                    expected_status: Some(vec![ExpectedStatus::Range(status.to_string())]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let retry = |status: &str| Rule {
+            actions: vec![Action {
+                retry: Some(RetryPolicy {
+                    status: Some(vec![ExpectedStatus::Range(status.to_string())]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        for status in ["200-299", "4xx", "204"] {
+            assert!(http(status).validate().is_ok(), "{}", status);
+            assert!(retry(status).validate().is_ok(), "{}", status);
+        }
+        for status in ["2xx-", "500-200", "6xx", "ok"] {
+            assert!(http(status).validate().is_err(), "{}", status);
+            assert!(retry(status).validate().is_err(), "{}", status);
+        }
+    }
+
+    #[test]
+    fn test_validate_rule_shell_limits() {
+        let limited = |memory_mb: u64| Rule {
+            actions: vec![Action {
+                shell: Some(ShellAction {
+                    command: "make".to_string(),
+                    limits: Some(ShellLimits {
+                        memory_mb: Some(memory_mb),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert!(limited(512).validate().is_ok());
+        assert!(limited(u64::MAX / 1024).validate().is_err());
+    }
+
+    #[test]
+    fn test_rule_max_parallel_default() {
+        let sequential = Rule {
+            actions: vec![create_test_action("a", &[]), create_test_action("b", &[])],
+            ..Default::default()
+        };
+        let graph = Rule {
+            actions: vec![
+                create_test_action("a", &[]),
+                create_test_action("b", &["a"]),
+            ],
+            ..Default::default()
+        };
+        let configured = Rule {
+            max_parallel: Some(2),
+            ..graph.clone()
+        };
+
+        assert_eq!(sequential.max_parallel(), 1);
+        assert_eq!(graph.max_parallel(), DEFAULT_MAX_PARALLEL);
+        assert_eq!(configured.max_parallel(), 2);
+    }
+
+    #[test]
+    fn test_validate_rule_schedule() {
+        let schedule = |timezone: &str, start: &str| Rule {
+            schedule: Some(Schedule {
+                timezone: Some(timezone.to_string()),
+                block: Some(vec![TimeWindow {
+                    start: Some(start.to_string()),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(schedule("Europe/London", "17:00").validate().is_ok());
+        assert!(schedule("Not/A_Zone", "17:00").validate().is_err());
+        assert!(schedule("Europe/London", "25:99").validate().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_bitbucket_action() {
+        let yaml = r#"
+comment:
+  text: "Deployed {{ event.branch }}"
+  key: "deploy"
+build_status:
+  state: SUCCESSFUL
+  key: "deploy"
+  url: "https://ci.example.com/builds/1"
+reviewers: ["alice"]
+pull_request: merge
+"#;
+
+        let action: BitbucketAction = serde_yaml::from_str(yaml).unwrap();
+        let empty = Rule {
+            actions: vec![Action {
+                bitbucket: Some(BitbucketAction::default()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(action.comment.unwrap().key.as_deref(), Some("deploy"));
+        assert_eq!(action.build_status.unwrap().state, BuildState::Successful);
+        assert_eq!(action.pull_request, Some(PullRequestOperation::Merge));
+        assert!(serde_yaml::from_str::<BitbucketAction>("pull_request: rebase").is_err());
+        assert!(empty.validate().is_err());
+    }
+
     #[test]
     fn test_deserialize_rule() {
         let yaml = r#"
 description: "Test rule"
 webhooks:
   - "test-webhook"
+priority: 5
+stop: true
+enabled: false
+dry_run: true
 event_types:
   - "pr_created"
 branches:
@@ -139,16 +1155,65 @@ paths:
   - exact: "src/main.rs"
   - pattern: "src/*.rs"
   - regex: "docs/.*\\.md"
+schedule:
+  timezone: "Europe/London"
+  block:
+    - days: ["Fri", "saturday"]
+      start: "17:00"
+  freezes: ["holidays"]
+  queue: true
+pull_request:
+  title:
+    - not:
+        regex: "WIP"
+  authors:
+    - username: "renovate"
+    - users: ["alice", "bob@example.com"]
+    - group_file: "/etc/git-actions/bots.txt"
 actions:
-  - http:
-      method: "GET"
+  - id: "deploy"
+    retry:
+      max_attempts: 5
+      initial_backoff_ms: 200
+      multiplier: 3
+      jitter: 0
+      status: [503]
+      errors: ["timeout", "exit"]
+    http:
+      method: "PUT"
       url: "https://example.com"
+      query:
+        branch: "{{ event.branch }}"
       headers:
         Content-Type: "application/json"
+      auth:
+        basic:
+          username: "ci"
+          password: "{{ env.CI_PASSWORD }}"
       body: '{"key": "value"}'
+      timeout: 5
+      max_redirects: 0
+      expected_status: [200, "300-303", "4xx"]
+      outputs:
+        deployment_id:
+          json: "/deployment/id"
+        build:
+          header: "Location"
+          regex: "builds/(\\d+)"
+      insecure_skip_verify: true
   - shell:
       command: "echo 'Hello, world!'"
       working_dir: "/tmp"
+      shell: "bash"
+      environment:
+        BRANCH: "{{ event.branch }}"
+      env_allowlist: ["PATH"]
+      uid: 1000
+      gid: 1000
+      limits:
+        cpu_seconds: 60
+        memory_mb: 512
+      timeout: 30
 "#;
 
         let rule: Rule = serde_yaml::from_str(yaml).unwrap();
@@ -157,6 +1222,10 @@ actions:
         assert_eq!(rule.description, Some("Test rule".to_string()));
         assert_eq!(rule.webhooks, vec!["test-webhook"]);
         assert_eq!(rule.event_types, Some(vec!["pr_created".to_string()]));
+        assert_eq!(rule.priority, 5);
+        assert!(rule.stop);
+        assert!(!rule.is_enabled());
+        assert!(rule.dry_run);
 
         // Check branch filters
         assert_eq!(rule.branches.as_ref().unwrap().len(), 3);
@@ -198,13 +1267,108 @@ actions:
             panic!("Expected Regex path filter");
         }
 
+        // Check schedule
+        let schedule = rule.schedule.as_ref().unwrap();
+        assert_eq!(schedule.timezone, Some("Europe/London".to_string()));
+        assert!(schedule.allow.is_none());
+        assert_eq!(
+            schedule.block,
+            Some(vec![TimeWindow {
+                days: Some(vec![Weekday::Fri, Weekday::Sat]),
+                start: Some("17:00".to_string()),
+                end: None,
+            }])
+        );
+        assert_eq!(schedule.freezes, Some(vec!["holidays".to_string()]));
+        assert!(schedule.queue);
+
+        // Check pull request filters
+        let pull_request = rule.pull_request.as_ref().unwrap();
+        assert_eq!(
+            pull_request.title,
+            Some(vec![TextFilter::Not {
+                not: Box::new(TextFilter::Regex {
+                    regex: "WIP".to_string()
+                })
+            }])
+        );
+        assert!(pull_request.description.is_none());
+        assert_eq!(
+            pull_request.authors,
+            Some(vec![
+                AuthorFilter::Username {
+                    username: "renovate".to_string()
+                },
+                AuthorFilter::Users {
+                    users: vec!["alice".to_string(), "bob@example.com".to_string()]
+                },
+                AuthorFilter::GroupFile {
+                    group_file: "/etc/git-actions/bots.txt".to_string(),
+                    members: vec![],
+                },
+            ])
+        );
+
         // Check actions
         assert_eq!(rule.actions.len(), 2);
 
         // Check HTTP action
+        assert_eq!(rule.actions[0].id.as_deref(), Some("deploy"));
+        assert_eq!(
+            rule.actions[0].retry,
+            Some(RetryPolicy {
+                max_attempts: Some(5),
+                initial_backoff_ms: Some(200),
+                max_backoff_ms: None,
+                multiplier: Some(3.0),
+                jitter: Some(0.0),
+                status: Some(vec![ExpectedStatus::Code(503)]),
+                errors: Some(vec![RetryableError::Timeout, RetryableError::Exit]),
+            })
+        );
         let http_action = rule.actions[0].http.as_ref().unwrap();
-        assert_eq!(http_action.method, "GET");
+        assert_eq!(http_action.method, "PUT");
         assert_eq!(http_action.url, "https://example.com");
+        assert_eq!(
+            http_action.query.as_ref().unwrap().get("branch").unwrap(),
+            "{{ event.branch }}"
+        );
+        assert_eq!(
+            http_action.auth,
+            Some(HttpAuth::Basic {
+                basic: BasicAuth {
+                    username: "ci".to_string(),
+                    password: Some("{{ env.CI_PASSWORD }}".to_string()),
+                }
+            })
+        );
+        assert_eq!(http_action.timeout, Some(5));
+        assert_eq!(http_action.max_redirects, Some(0));
+        assert_eq!(
+            http_action.expected_status,
+            Some(vec![
+                ExpectedStatus::Code(200),
+                ExpectedStatus::Range("300-303".to_string()),
+                ExpectedStatus::Range("4xx".to_string()),
+            ])
+        );
+        assert!(http_action.insecure_skip_verify);
+        let outputs = http_action.outputs.as_ref().unwrap();
+        assert_eq!(
+            outputs.get("deployment_id"),
+            Some(&OutputExtractor {
+                json: Some("/deployment/id".to_string()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            outputs.get("build"),
+            Some(&OutputExtractor {
+                header: Some("Location".to_string()),
+                regex: Some("builds/(\\d+)".to_string()),
+                ..Default::default()
+            })
+        );
         assert_eq!(
             http_action
                 .headers
@@ -220,6 +1384,78 @@ actions:
         let shell_action = rule.actions[1].shell.as_ref().unwrap();
         assert_eq!(shell_action.command, "echo 'Hello, world!'");
         assert_eq!(shell_action.working_dir.as_ref().unwrap(), "/tmp");
+        assert_eq!(shell_action.shell.as_deref(), Some("bash"));
+        assert_eq!(
+            shell_action
+                .environment
+                .as_ref()
+                .unwrap()
+                .get("BRANCH")
+                .unwrap(),
+            "{{ event.branch }}"
+        );
+        assert_eq!(
+            shell_action.env_allowlist.as_deref(),
+            Some(&["PATH".to_string()][..])
+        );
+        assert_eq!(shell_action.uid, Some(1000));
+        assert_eq!(shell_action.gid, Some(1000));
+        assert_eq!(
+            shell_action.limits,
+            Some(ShellLimits {
+                cpu_seconds: Some(60),
+                memory_mb: Some(512),
+                open_files: None,
+            })
+        );
+        assert_eq!(shell_action.timeout, Some(30));
+    }
+
+    #[test]
+    fn test_deserialize_http_action_bodies() {
+        // Setup
+        let yaml = r#"
+method: "POST"
+url: "https://example.com"
+json:
+  title: "{{ event.pull_request.title }}"
+  labels: ["ci", "{{ event.branch }}"]
+  draft: false
+form:
+  branch: "{{ event.branch }}"
+multipart:
+  branch: "{{ event.branch }}"
+  report:
+    file: "/tmp/report.xml"
+"#;
+
+        // Execute
+        let action: HttpAction = serde_yaml::from_str(yaml).unwrap();
+
+        // Verify
+        assert_eq!(
+            action.json,
+            Some(serde_json::json!({
+                "title": "{{ event.pull_request.title }}",
+                "labels": ["ci", "{{ event.branch }}"],
+                "draft": false,
+            }))
+        );
+        assert_eq!(
+            action.form.unwrap().get("branch").unwrap(),
+            "{{ event.branch }}"
+        );
+        let multipart = action.multipart.unwrap();
+        assert_eq!(
+            multipart.get("branch"),
+            Some(&MultipartField::Text("{{ event.branch }}".to_string()))
+        );
+        assert_eq!(
+            multipart.get("report"),
+            Some(&MultipartField::File {
+                file: "/tmp/report.xml".to_string()
+            })
+        );
     }
 
     #[test]
@@ -270,6 +1506,14 @@ spec:
         assert_eq!(rule1.webhooks, vec!["webhook1"]);
         assert_eq!(rule1.event_types, Some(vec!["pr_created".to_string()]));
         assert_eq!(rule1.actions.len(), 1);
+        assert_eq!(rule1.priority, 0);
+        assert!(!rule1.stop);
+        assert!(rule1.is_enabled());
+        assert!(!rule1.dry_run);
+
+        // Check declaration order is kept
+        let names: Vec<&String> = config.spec.rules.keys().collect();
+        assert_eq!(names, vec!["rule1", "rule2"]);
 
         // Check rule2
         let rule2 = &config.spec.rules["rule2"];
@@ -33,6 +33,10 @@ pub struct ServerSpec {
     pub host: String,
     pub tls: Option<TlsSpec>,
     pub logging: Option<LoggingSpec>,
+    /// How the actions of matched rules are executed
+    pub execution: Option<ExecutionSpec>,
+    /// Bearer token required by the admin endpoints
+    pub admin_token: Option<String>,
     /// Configuration files to load (webhooks and rules)
     pub configs: Vec<String>,
 }
@@ -44,6 +48,8 @@ impl Default for ServerSpec {
             host: "0.0.0.0".to_string(),
             tls: None,
             logging: Some(LoggingSpec::default()),
+            execution: None,
+            admin_token: None,
             configs: Vec::new(),
         }
     }
@@ -73,6 +79,48 @@ impl Default for LoggingSpec {
     }
 }
 
+/// Action execution configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutionSpec {
+    /// Run actions before responding (sync) or in background workers (async)
+    pub mode: ExecutionMode,
+    /// Number of actions run concurrently by the background workers
+    pub workers: usize,
+    /// Number of queued rule runs waiting for a worker
+    pub queue_size: usize,
+    /// File persisting the queue so that pending rule runs survive a restart
+    pub queue_file: Option<PathBuf>,
+    /// Directory storing rule runs whose actions failed, for inspection and replay
+    pub dead_letter_dir: Option<PathBuf>,
+    /// Response status when some of the matched rules failed and others succeeded
+    pub partial_failure_status: u16,
+}
+
+impl Default for ExecutionSpec {
+    fn default() -> Self {
+        Self {
+            mode: ExecutionMode::Sync,
+            workers: 4,
+            queue_size: 100,
+            queue_file: None,
+            dead_letter_dir: None,
+            partial_failure_status: 207,
+        }
+    }
+}
+
+/// Action execution mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    /// The webhook responds once all actions finished
+    #[default]
+    Sync,
+    /// The webhook responds with 202 and a run id once the actions are queued
+    Async,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +142,11 @@ spec:
   logging:
     level: info
     format: json
+  execution:
+    mode: async
+    workers: 8
+    queue_file: "/var/lib/git-actions/queue.jsonl"
+    dead_letter_dir: "/var/lib/git-actions/dead-letters"
   configs:
     - "rules.yaml"
     - "webhooks.yaml"
@@ -112,6 +165,18 @@ spec:
         }
         assert_eq!(config.spec.host, "127.0.0.1");
         assert_eq!(config.spec.port, 8080);
+        let execution = config.spec.execution.unwrap();
+        assert_eq!(execution.mode, ExecutionMode::Async);
+        assert_eq!(execution.workers, 8);
+        assert_eq!(execution.queue_size, 100);
+        assert_eq!(
+            execution.queue_file,
+            Some(PathBuf::from("/var/lib/git-actions/queue.jsonl"))
+        );
+        assert_eq!(
+            execution.dead_letter_dir,
+            Some(PathBuf::from("/var/lib/git-actions/dead-letters"))
+        );
         assert_eq!(config.spec.configs.len(), 2);
         assert_eq!(config.spec.configs[0], "rules.yaml");
         assert_eq!(config.spec.configs[1], "webhooks.yaml");
@@ -1,10 +1,10 @@
-use super::{Rule, RulesConfig, WebhookConfig};
+use super::{FreezeCalendarConfig, Rule, RulesConfig, WebhookConfig};
 use anyhow::Result;
 use anyhow::{bail, Context};
 use glob::glob;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
-use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -13,6 +13,7 @@ pub enum ConfigType {
     // Server(ServerConfig),
     Webhook(WebhookConfig),
     Rules(RulesConfig),
+    FreezeCalendar(FreezeCalendarConfig),
 }
 
 #[derive(Clone, Debug)]
@@ -48,6 +49,24 @@ impl Config {
             }
         }
 
+        self.validate_references()
+    }
+
+    /// Check that the freeze calendars referenced by rule schedules are configured
+    fn validate_references(&self) -> Result<()> {
+        for config in &self.configs {
+            if let ConfigType::Rules(rules_config) = config {
+                for (name, rule) in rules_config.spec.rules.iter() {
+                    let freezes = rule.schedule.iter().flat_map(|s| s.freezes.iter());
+                    for freezes in freezes {
+                        self.find_freeze_calendars(freezes).with_context(|| {
+                            format!("Invalid rule {}-{}", rules_config.metadata.name, name)
+                        })?;
+                    }
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -70,10 +89,29 @@ impl Config {
                 ConfigType::Webhook(webhook_config)
             }
             Some("Rules") => {
-                let rules_config: RulesConfig = serde_yaml::from_value(content.clone())
+                let mut rules_config: RulesConfig = serde_yaml::from_value(content.clone())
                     .with_context(|| format!("Failed to parse rules config: {}", path.display()))?;
+                for (name, rule) in rules_config.spec.rules.iter_mut() {
+                    rule.validate()
+                        .and_then(|_| rule.load_files())
+                        .with_context(|| format!("Invalid rule {} in {}", name, path.display()))?;
+                }
                 ConfigType::Rules(rules_config)
             }
+            Some("FreezeCalendar") => {
+                let calendar_config: FreezeCalendarConfig = serde_yaml::from_value(content.clone())
+                    .with_context(|| {
+                        format!("Failed to parse freeze calendar config: {}", path.display())
+                    })?;
+                calendar_config.spec.validate().with_context(|| {
+                    format!(
+                        "Invalid freeze calendar {} in {}",
+                        calendar_config.metadata.name,
+                        path.display()
+                    )
+                })?;
+                ConfigType::FreezeCalendar(calendar_config)
+            }
             _ => {
                 bail!("Unknown config type: {:?}", kind);
             }
@@ -93,8 +131,9 @@ impl Config {
         bail!("Webhook not found for path: {}", path);
     }
 
-    pub fn find_rules_by_webhook(&self, webhook_name: &str) -> Result<HashMap<String, &Rule>> {
-        let mut rules = HashMap::new();
+    /// Find the rules subscribed to a webhook, in the order they were declared
+    pub fn find_rules_by_webhook(&self, webhook_name: &str) -> Result<IndexMap<String, &Rule>> {
+        let mut rules = IndexMap::new();
 
         for config in &self.configs {
             if let ConfigType::Rules(rules_config) = config {
@@ -109,12 +148,47 @@ impl Config {
 
         Ok(rules)
     }
+
+    /// Find a rule by its full name, "<rules config name>-<rule name>"
+    pub fn find_rule(&self, rule_name: &str) -> Option<&Rule> {
+        self.configs.iter().find_map(|config| match config {
+            ConfigType::Rules(rules_config) => {
+                rules_config.spec.rules.iter().find_map(|(name, rule)| {
+                    (format!("{}-{}", rules_config.metadata.name, name) == rule_name)
+                        .then_some(rule)
+                })
+            }
+            _ => None,
+        })
+    }
+
+    /// Find freeze calendars by name, failing if any of them is not configured
+    pub fn find_freeze_calendars(&self, names: &[String]) -> Result<Vec<&FreezeCalendarConfig>> {
+        let mut calendars = Vec::new();
+
+        for name in names {
+            let calendar = self.configs.iter().find_map(|config| match config {
+                ConfigType::FreezeCalendar(calendar) if &calendar.metadata.name == name => {
+                    Some(calendar)
+                }
+                _ => None,
+            });
+
+            match calendar {
+                Some(calendar) => calendars.push(calendar),
+                None => bail!("Freeze calendar not found: {}", name),
+            }
+        }
+
+        Ok(calendars)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ConfigKind {
     Webhook,
     Rules,
+    FreezeCalendar,
 }
 
 /// API version for configuration files
@@ -126,3 +200,57 @@ pub struct Metadata {
     /// Name of the resource
     pub name: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const RULES: &str = r#"
+apiVersion: v1
+kind: Rules
+metadata:
+  name: deploy
+spec:
+  rules:
+    prod:
+      webhooks: ["bitbucket"]
+      schedule:
+        freezes: ["holidays"]
+      actions: []
+"#;
+
+    const CALENDAR: &str = r#"
+apiVersion: v1
+kind: FreezeCalendar
+metadata:
+  name: holidays
+spec:
+  periods:
+    - start: "2025-12-24"
+      end: "2026-01-02"
+"#;
+
+    #[test]
+    fn test_load_checks_freeze_calendar_references() {
+        // Setup
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("rules.yaml"), RULES).unwrap();
+        let pattern = vec![dir.path().join("*.yaml").display().to_string()];
+
+        // Execute
+        let missing = Config::new().load(&pattern);
+        fs::write(dir.path().join("calendar.yaml"), CALENDAR).unwrap();
+        let found = Config::new().load(&pattern);
+
+        // Verify
+        let error = format!("{:#}", missing.unwrap_err());
+        assert!(error.contains("deploy-prod"), "{}", error);
+        assert!(
+            error.contains("Freeze calendar not found: holidays"),
+            "{}",
+            error
+        );
+        assert!(found.is_ok());
+    }
+}
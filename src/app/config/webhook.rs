@@ -21,6 +21,21 @@ pub struct WebhookSpec {
 
     /// bitbucket specific configuration
     pub bitbucket: Option<Bitbucket>,
+
+    /// Projects of a monorepo the changed files are grouped into, exposed as
+    /// `event.projects`
+    pub projects: Option<Vec<ProjectSpec>>,
+}
+
+/// Monorepo project definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ProjectSpec {
+    /// Named project made of the files matching any of the globs
+    Paths { name: String, paths: Vec<String> },
+    /// Every directory holding the marker file, e.g. `Cargo.toml`, is a project
+    /// named after the directory's path. A file belongs to its closest project
+    Marker { marker: String },
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -2,7 +2,6 @@ use axum::response::{IntoResponse, Response};
 use serde_json::json;
 
 // TODO use thiserror
-// TODO classify errors?
 #[derive(Debug)]
 pub enum Error {
     /// Invalid webhook configuration
@@ -15,34 +14,63 @@ pub enum Error {
     Handler(String),
     /// Error performing the action
     Action(String),
+    /// Action attempt that failed in a way a retry policy can match
+    ActionFailed(FailureKind, String),
+    /// The background action queue is full
+    QueueFull(String),
+    /// Dead letter not found given its id
+    DeadLetterNotFound(String),
+    /// Missing or invalid admin token
+    Unauthorized,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::WebhookNotFoundForPath(path) => write!(f, "webhook not found for path: {path}"),
+            Error::RulesNotFoundForWebhook(webhook_name) => {
+                write!(f, "rules not found for webhook: {webhook_name}")
+            }
+            Error::Action(message) | Error::ActionFailed(_, message) => {
+                write!(f, "action error: {message}")
+            }
+            Error::QueueFull(message) => write!(f, "action queue full: {message}"),
+            Error::DeadLetterNotFound(id) => write!(f, "dead letter not found: {id}"),
+            Error::Unauthorized => write!(f, "missing or invalid admin token"),
+            Error::WebhookConfig(message) => write!(f, "webhook configuration error: {message}"),
+            Error::Handler(message) => write!(f, "handler error: {message}"),
+        }
+    }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            Error::WebhookNotFoundForPath(path) => (
-                axum::http::StatusCode::NOT_FOUND,
-                format!("webhook not found for path: {path}"),
-            ),
-            Error::RulesNotFoundForWebhook(webhook_name) => (
-                axum::http::StatusCode::NOT_FOUND,
-                format!("rules not found for webhook: {webhook_name}"),
-            ),
-            Error::Action(message) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("action error: {message}"),
-            ),
-            Error::WebhookConfig(message) => (
-                axum::http::StatusCode::BAD_REQUEST,
-                format!("webhook configuration error: {message}"),
-            ),
-            Error::Handler(message) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("handler error: {message}"),
-            ),
+        let status = match self {
+            Error::WebhookNotFoundForPath(_)
+            | Error::RulesNotFoundForWebhook(_)
+            | Error::DeadLetterNotFound(_) => axum::http::StatusCode::NOT_FOUND,
+            Error::QueueFull(_) => axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            Error::Unauthorized => axum::http::StatusCode::UNAUTHORIZED,
+            Error::WebhookConfig(_) => axum::http::StatusCode::BAD_REQUEST,
+            Error::Action(_) | Error::ActionFailed(..) | Error::Handler(_) => {
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR
+            }
         };
 
-        let body = axum::Json(json!({"error": error_message}));
+        let body = axum::Json(json!({"error": self.to_string()}));
         (status, body).into_response()
     }
 }
+
+/// Kind of a failed action attempt
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FailureKind {
+    /// The request or command timed out
+    Timeout,
+    /// The HTTP connection could not be established
+    Connect,
+    /// The HTTP response had an unexpected status
+    Status(u16),
+    /// The shell command exited with a non-zero status, None when killed by a signal
+    Exit(Option<i32>),
+}
@@ -0,0 +1,313 @@
+use crate::app::{
+    config::server::ExecutionMode,
+    worker::{dead_letter::DeadLetterStore, exec_job, Job},
+    AppState, Error,
+};
+use axum::{
+    extract::{Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
+
+/// List the dead letters, oldest first
+#[axum::debug_handler]
+pub async fn list_dead_letters(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, Error> {
+    let store = authorize(&state, &headers)?;
+
+    let dead_letters: Vec<_> = store
+        .list()?
+        .into_iter()
+        .map(|dead_letter| {
+            json!({
+                "id": dead_letter.id,
+                "run_id": dead_letter.run_id,
+                "rule_name": dead_letter.rule_name,
+                "failed_at": dead_letter.failed_at,
+                "error": dead_letter.error,
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({ "dead_letters": dead_letters })))
+}
+
+/// Inspect a dead letter with its rendered request, event and error
+#[axum::debug_handler]
+pub async fn get_dead_letter(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, Error> {
+    let store = authorize(&state, &headers)?;
+
+    Ok(Json(store.get(&id)?))
+}
+
+/// Run the actions of a dead letter's rule again and remove the dead letter once the
+/// replay was queued or succeeded. The rule runs again from its first action, not
+/// from the failed one, so that the outputs of the earlier actions are available.
+/// A replay that fails replaces the dead letter, or is stored as a new dead letter
+/// when it ran on the workers
+#[axum::debug_handler]
+pub async fn replay_dead_letter(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, Error> {
+    let store = authorize(&state, &headers)?;
+
+    let mut job = store.get(&id)?.job;
+    job.restore(&state.config);
+    match state
+        .workers
+        .as_ref()
+        .filter(|_| state.mode == ExecutionMode::Async)
+    {
+        Some(workers) => {
+            job.id = Uuid::new_v4().to_string();
+            info!("Replaying dead letter {} as job {}", id, job.id);
+            let response = replay_response(&id, &job);
+            workers.submit(vec![job]).await?;
+            store.remove(&id)?;
+            Ok((StatusCode::ACCEPTED, Json(response)))
+        }
+        None => {
+            // a failed replay stores its dead letter under the same id
+            job.id = id.clone();
+            info!("Replaying dead letter {}", id);
+            let mut response = replay_response(&id, &job);
+            response["actions"] = json!(exec_job(&job, Some(store)).await?);
+            store.remove(&id)?;
+            Ok((StatusCode::OK, Json(response)))
+        }
+    }
+}
+
+fn replay_response(id: &str, job: &Job) -> serde_json::Value {
+    json!({
+        "message": format!("Dead letter replayed: {}", id),
+        "run_id": job.run_id,
+        "job_id": job.id,
+    })
+}
+
+/// Discard a dead letter
+#[axum::debug_handler]
+pub async fn discard_dead_letter(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, Error> {
+    let store = authorize(&state, &headers)?;
+
+    store.remove(&id)?;
+    info!("Discarded dead letter {}", id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Check the admin token and return the dead letter store. Without a configured
+/// admin token every request is refused
+fn authorize<'a>(state: &'a AppState, headers: &HeaderMap) -> Result<&'a DeadLetterStore, Error> {
+    let Some(token) = &state.admin_token else {
+        return Err(Error::Unauthorized);
+    };
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !bearer.is_some_and(|bearer| constant_time_eq(bearer.as_bytes(), token.as_bytes())) {
+        return Err(Error::Unauthorized);
+    }
+
+    state
+        .dead_letters
+        .as_deref()
+        .ok_or_else(|| Error::DeadLetterNotFound("dead letters are not enabled".to_string()))
+}
+
+/// Compare two byte strings in a time independent of where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        actions::FailedAction,
+        config::{rules::HttpAction, Action, Config, Rule},
+        worker::dead_letter::DeadLetter,
+    };
+    use tempfile::{tempdir, TempDir};
+    use tera::Context;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn create_test_state(url: &str) -> (Arc<AppState>, TempDir) {
+        let dir = tempdir().unwrap();
+        let store = DeadLetterStore::open(dir.path()).unwrap();
+        let job = Job {
+            id: "dead-1".to_string(),
+            run_id: "run-1".to_string(),
+            rule_name: "rule".to_string(),
+            rule: Rule {
+                actions: vec![Action {
+                    http: Some(HttpAction {
+                        method: "POST".to_string(),
+                        url: url.to_string(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            context: Context::new(),
+            not_before: None,
+        };
+        let failed = FailedAction {
+            index: 0,
+            request: json!({}),
+            error: Error::Action("failed".to_string()),
+            results: Vec::new(),
+        };
+        store.add(&DeadLetter::new(&job, &failed)).unwrap();
+
+        let state = AppState {
+            config: Config::new(),
+            mode: ExecutionMode::Sync,
+            workers: None,
+            dead_letters: Some(Arc::new(store)),
+            admin_token: Some("token".to_string()),
+            partial_failure_status: StatusCode::MULTI_STATUS,
+        };
+        (Arc::new(state), dir)
+    }
+
+    fn admin_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, "Bearer token".parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_require_admin_token() {
+        let (state, _dir) = create_test_state("http://localhost");
+
+        let result = list_dead_letters(State(state), HeaderMap::new()).await;
+
+        assert!(matches!(result, Err(Error::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_refused_without_admin_token() {
+        // Setup
+        let (state, _dir) = create_test_state("http://localhost");
+        let mut no_token = (*state).clone();
+        no_token.admin_token = None;
+        let mut wrong = HeaderMap::new();
+        wrong.insert(AUTHORIZATION, "Bearer tokem".parse().unwrap());
+
+        // Execute
+        let open = list_dead_letters(State(Arc::new(no_token)), admin_headers()).await;
+        let wrong_token = list_dead_letters(State(state), wrong).await;
+
+        // Verify
+        assert!(matches!(open, Err(Error::Unauthorized)));
+        assert!(matches!(wrong_token, Err(Error::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_list_and_get_dead_letters() {
+        let (state, _dir) = create_test_state("http://localhost");
+
+        let list = list_dead_letters(State(state.clone()), admin_headers()).await;
+        let get = get_dead_letter(Path("dead-1".to_string()), State(state), admin_headers()).await;
+
+        assert_eq!(list.unwrap().into_response().status(), StatusCode::OK);
+        assert_eq!(get.unwrap().into_response().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_replay_dead_letter() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let (state, _dir) = create_test_state(&mock_server.uri());
+
+        // Execute
+        let result = replay_dead_letter(
+            Path("dead-1".to_string()),
+            State(state.clone()),
+            admin_headers(),
+        )
+        .await;
+
+        // Verify
+        assert_eq!(result.unwrap().into_response().status(), StatusCode::OK);
+        let store = state.dead_letters.as_ref().unwrap();
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_replay_keeps_dead_letter() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+        let (state, _dir) = create_test_state(&mock_server.uri());
+
+        // Execute
+        let result = replay_dead_letter(
+            Path("dead-1".to_string()),
+            State(state.clone()),
+            admin_headers(),
+        )
+        .await;
+
+        // Verify
+        assert!(result.is_err());
+        let store = state.dead_letters.as_ref().unwrap();
+        let dead_letters = store.list().unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].id, "dead-1");
+        assert!(dead_letters[0].error.contains("500"));
+    }
+
+    #[tokio::test]
+    async fn test_discard_dead_letter() {
+        let (state, _dir) = create_test_state("http://localhost");
+
+        let result = discard_dead_letter(
+            Path("dead-1".to_string()),
+            State(state.clone()),
+            admin_headers(),
+        )
+        .await;
+        let missing =
+            discard_dead_letter(Path("dead-1".to_string()), State(state), admin_headers()).await;
+
+        assert_eq!(
+            result.unwrap().into_response().status(),
+            StatusCode::NO_CONTENT
+        );
+        assert!(matches!(missing, Err(Error::DeadLetterNotFound(_))));
+    }
+}
@@ -1,5 +1,9 @@
+pub mod dead_letters;
 pub mod health;
 pub mod metrics;
 
+pub use dead_letters::{
+    discard_dead_letter, get_dead_letter, list_dead_letters, replay_dead_letter,
+};
 pub use health::health_check;
 pub use metrics::metrics;
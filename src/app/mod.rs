@@ -1,3 +1,5 @@
+pub mod actions;
+pub mod cli;
 pub mod config;
 mod error;
 mod handlers;
@@ -6,17 +8,32 @@ mod router;
 mod server;
 pub mod template;
 pub mod webhooks;
+pub mod worker;
 
 pub use config::{Config, ServerConfig};
-pub use error::Error;
+pub use error::{Error, FailureKind};
 
 use anyhow::{Context, Result};
+use axum::http::StatusCode;
+use config::server::ExecutionMode;
 use server::Server;
+use std::sync::Arc;
 
 /// Application state shared across request handlers
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub config: Config,
+    /// How the actions of matched rules are executed
+    pub mode: ExecutionMode,
+    /// Background workers running the actions in async execution mode, and the
+    /// actions queued until their rule's schedule opens in both modes
+    pub workers: Option<worker::WorkerPool>,
+    /// Failed rule runs, inspected and replayed through the admin endpoints
+    pub dead_letters: Option<Arc<worker::dead_letter::DeadLetterStore>>,
+    /// Bearer token required by the admin endpoints
+    pub admin_token: Option<String>,
+    /// Webhook response status when some of the matched rules failed
+    pub partial_failure_status: StatusCode,
 }
 
 /// Run the HTTP server with the given configuration
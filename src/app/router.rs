@@ -4,16 +4,34 @@ use axum::Router;
 use std::sync::Arc;
 use tower_http::trace::TraceLayer;
 
-/// Create a router with all routes for the webhooks
-pub fn create_router() -> Router<Arc<AppState>> {
+/// Create a router with all routes for the webhooks. The admin routes are only
+/// registered when an admin token is configured
+pub fn create_router(admin: bool) -> Router<Arc<AppState>> {
     // Create main router
-    Router::new()
+    let router = Router::new()
         // health check endpoint
         .route("/health", get(handlers::health_check))
         // metrics endpoint
         .route("/metrics", get(handlers::metrics))
         // webhook endpoint
-        .route("/webhook/{*path}", post(webhooks::handler))
-        // add tracing layer for logging
-        .layer(TraceLayer::new_for_http())
+        .route("/webhook/{*path}", post(webhooks::handler));
+
+    // dead letter admin endpoints
+    let router = if admin {
+        router
+            .route("/admin/dead-letters", get(handlers::list_dead_letters))
+            .route(
+                "/admin/dead-letters/{id}",
+                get(handlers::get_dead_letter).delete(handlers::discard_dead_letter),
+            )
+            .route(
+                "/admin/dead-letters/{id}/replay",
+                post(handlers::replay_dead_letter),
+            )
+    } else {
+        router
+    };
+
+    // add tracing layer for logging
+    router.layer(TraceLayer::new_for_http())
 }
@@ -1,11 +1,13 @@
 use anyhow::{Context, Result};
+use axum::http::StatusCode;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tokio::signal;
-use tracing::info;
+use tracing::{info, warn};
 
 use super::config::{Config, ServerConfig};
 use super::router;
+use super::worker::{dead_letter::DeadLetterStore, WorkerPool};
 use super::AppState;
 
 /// HTTP server for Git-Actions
@@ -36,11 +38,45 @@ impl Server {
         info!("Server listening on {}", addr);
 
         // create a router
-        let app = router::create_router();
+        let admin_token = self.server_config.spec.admin_token.clone();
+        let app = router::create_router(admin_token.is_some());
+
+        // start the background workers
+        let execution = self
+            .server_config
+            .spec
+            .execution
+            .clone()
+            .unwrap_or_default();
+        let dead_letters = execution
+            .dead_letter_dir
+            .as_deref()
+            .map(DeadLetterStore::open)
+            .transpose()?
+            .map(Arc::new);
+        if dead_letters.is_some() && admin_token.is_none() {
+            warn!("Dead letter admin endpoints are disabled: admin_token is not set");
+        }
+        // the workers also run the actions queued until their rule's schedule opens
+        let workers = WorkerPool::start(&execution, &self.app_config, dead_letters.clone())
+            .with_context(|| "Failed to start action workers")?;
+
+        let partial_failure_status = StatusCode::from_u16(execution.partial_failure_status)
+            .with_context(|| {
+                format!(
+                    "Invalid partial failure status: {}",
+                    execution.partial_failure_status
+                )
+            })?;
 
         // add app state
         let state = AppState {
             config: self.app_config.to_owned(),
+            mode: execution.mode,
+            workers: Some(workers),
+            dead_letters,
+            admin_token,
+            partial_failure_status,
         };
         let app = app.with_state(Arc::new(state));
 
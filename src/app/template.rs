@@ -1,5 +1,9 @@
+use crate::app::actions::FailedAction;
+use crate::app::config::WebhookConfig;
+use crate::app::webhooks::rule_evaluator::Evaluation;
 use crate::app::webhooks::types::Event;
-use serde_json::Value;
+use crate::app::{Error, FailureKind};
+use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::env;
 use tera::{Context, Tera};
@@ -18,13 +22,122 @@ pub fn build_template_context(event: &Event) -> Context {
     context.insert("event", &event_value);
 
     // Add all environment variables to the context
+    insert_env_context(&mut context);
+
+    context
+}
+
+/// Add the server's environment variables to the context as `env`
+pub fn insert_env_context(context: &mut Context) {
     let mut env_map = serde_json::Map::new();
     for (key, value) in env::vars() {
         env_map.insert(key, Value::String(value));
     }
     context.insert("env", &Value::Object(env_map));
+}
 
-    context
+/// Add the webhook the event came from to the context as `webhook`, with its
+/// name and provider configuration. Provider actions call its API; tokens are
+/// only referenced by their environment variable
+pub fn insert_webhook_context(context: &mut Context, webhook: &WebhookConfig) {
+    context.insert(
+        "webhook",
+        &json!({
+            "name": webhook.metadata.name,
+            "bitbucket": webhook.spec.bitbucket,
+        }),
+    );
+}
+
+/// Add the rule whose actions run to the context as `rule`, with its name
+pub fn insert_rule_context(context: &mut Context, rule_name: &str) {
+    context.insert("rule", &json!({ "name": rule_name }));
+}
+
+/// Add the evaluation of the matched rule to the context as `match`,
+/// e.g. `match.files` holds the changed files matched by the rule's path filters
+pub fn insert_match_context(context: &mut Context, evaluation: &Evaluation) {
+    let match_value = serde_json::to_value(evaluation).unwrap_or_else(|e| {
+        error!("Failed to serialize rule evaluation: {}", e);
+        Value::Null
+    });
+
+    context.insert("match", &match_value);
+}
+
+/// Add the outputs of a finished action to the context as `steps.<id>.outputs`,
+/// so that later actions of the rule can use them
+pub fn insert_step_context(context: &mut Context, id: &str, outputs: &HashMap<String, String>) {
+    let mut steps = context
+        .get("steps")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    steps.insert(id.to_string(), json!({ "outputs": outputs }));
+
+    context.insert("steps", &Value::Object(steps));
+}
+
+/// Add the failed action of a rule to the context as `failure`, e.g.
+/// `failure.error` and `failure.status`, for the rule's failure hooks
+pub fn insert_failure_context(context: &mut Context, failed: &FailedAction, id: Option<&str>) {
+    let result = failed.results.last();
+    let (status, exit_code) = match &failed.error {
+        Error::ActionFailed(FailureKind::Status(status), _) => (Some(*status), None),
+        Error::ActionFailed(FailureKind::Exit(exit_code), _) => (None, *exit_code),
+        _ => (None, None),
+    };
+
+    context.insert(
+        "failure",
+        &json!({
+            "index": failed.index,
+            "id": id,
+            "action": result.map(|result| &result.action),
+            "target": result.and_then(|result| result.target.as_ref()),
+            "error": failed.error.to_string(),
+            "status": status,
+            "exit_code": exit_code,
+        }),
+    );
+}
+
+/// Evaluate a template expression as a condition, e.g. `event.branch == "main"`.
+/// The expression may be wrapped in `{{ }}`; undefined values are false
+pub fn evaluate_condition(expression: &str, context: &Context) -> Result<bool, tera::Error> {
+    let expression = expression.trim();
+    let expression = expression
+        .strip_prefix("{{")
+        .and_then(|expression| expression.strip_suffix("}}"))
+        .unwrap_or(expression);
+
+    let rendered = render_template(
+        &format!("{{% if {} %}}true{{% endif %}}", expression),
+        context,
+    )?;
+    Ok(rendered == "true")
+}
+
+/// Render a `foreach` template into its items. The output is read as a JSON
+/// array, or otherwise as one item per line. Empty and repeated items are dropped
+pub fn render_items(template_str: &str, context: &Context) -> Result<Vec<Value>, tera::Error> {
+    let rendered = render_template(template_str, context)?;
+
+    let items = match serde_json::from_str::<Value>(rendered.trim()) {
+        Ok(Value::Array(items)) => items,
+        _ => rendered
+            .lines()
+            .map(|line| Value::String(line.trim().to_string()))
+            .collect(),
+    };
+
+    let mut unique = Vec::new();
+    for item in items {
+        if item != Value::Null && item != json!("") && !unique.contains(&item) {
+            unique.push(item);
+        }
+    }
+    Ok(unique)
 }
 
 pub fn render_template(template_str: &str, context: &Context) -> Result<String, tera::Error> {
@@ -37,6 +150,24 @@ pub fn render_template(template_str: &str, context: &Context) -> Result<String,
     Ok(result)
 }
 
+/// Render the string values of a JSON structure as templates, recursively
+pub fn render_template_value(value: &Value, context: &Context) -> Result<Value, tera::Error> {
+    match value {
+        Value::String(template_str) => Ok(Value::String(render_template(template_str, context)?)),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| render_template_value(item, context))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, field)| Ok((key.clone(), render_template_value(field, context)?)))
+            .collect::<Result<_, _>>()
+            .map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
 pub fn render_template_map(
     map: &HashMap<String, String>,
     context: &Context,
@@ -61,7 +192,7 @@ pub fn render_template_map(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::app::webhooks::types::{Event, EventType};
+    use crate::app::webhooks::types::{Event, EventType, PullRequest};
     use std::env;
 
     #[test]
@@ -70,6 +201,8 @@ mod tests {
             event_type: EventType::Opened,
             branch: "feature/test".to_string(),
             changed_files: vec!["src/main.rs".to_string(), "Cargo.toml".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
         };
 
         let context = build_template_context(&event);
@@ -98,6 +231,8 @@ mod tests {
             event_type: EventType::Opened,
             branch: "feature/test".to_string(),
             changed_files: vec!["src/main.rs".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
         };
 
         // Set a test environment variable
@@ -123,6 +258,8 @@ mod tests {
             event_type: EventType::Opened,
             branch: "feature/test".to_string(),
             changed_files: vec!["src/main.rs".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
         };
 
         // Set a test environment variable
@@ -155,6 +292,52 @@ mod tests {
         env::remove_var("CI_API_TOKEN");
     }
 
+    #[test]
+    fn test_insert_match_context() {
+        let event = Event {
+            event_type: EventType::Opened,
+            branch: "feature/test".to_string(),
+            changed_files: vec!["services/api/main.rs".to_string(), "README.md".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
+        };
+        let evaluation = Evaluation {
+            matched: true,
+            files: vec!["services/api/main.rs".to_string()],
+            ..Default::default()
+        };
+
+        let mut context = build_template_context(&event);
+        insert_match_context(&mut context, &evaluation);
+
+        let rendered = render_template(
+            "{% for file in match.files %}{{ file }};{% endfor %}",
+            &context,
+        )
+        .unwrap();
+        assert_eq!(rendered, "services/api/main.rs;");
+    }
+
+    #[test]
+    fn test_insert_step_context() {
+        // Setup
+        let mut context = Context::new();
+        let deploy = HashMap::from([("id".to_string(), "42".to_string())]);
+        let build = HashMap::from([("number".to_string(), "7".to_string())]);
+
+        // Execute
+        insert_step_context(&mut context, "deploy", &deploy);
+        insert_step_context(&mut context, "build", &build);
+
+        // Verify
+        let rendered = render_template(
+            "{{ steps.deploy.outputs.id }}-{{ steps.build.outputs.number }}",
+            &context,
+        )
+        .unwrap();
+        assert_eq!(rendered, "42-7");
+    }
+
     #[test]
     fn test_render_template() {
         let mut context = Context::new();
@@ -172,6 +355,37 @@ mod tests {
         assert_eq!(result, "Hello, world!");
     }
 
+    #[test]
+    fn test_render_template_value() {
+        // Setup
+        let mut context = Context::new();
+        context.insert("title", r#"Fix "quoted" title"#);
+        let value = json!({
+            "title": "{{ title }}",
+            "labels": ["ci", "{{ title | lower }}"],
+            "count": 1,
+            "draft": false,
+        });
+
+        // Execute
+        let rendered = render_template_value(&value, &context).unwrap();
+
+        // Verify
+        assert_eq!(
+            rendered,
+            json!({
+                "title": r#"Fix "quoted" title"#,
+                "labels": ["ci", r#"fix "quoted" title"#],
+                "count": 1,
+                "draft": false,
+            })
+        );
+        assert_eq!(
+            serde_json::to_string(&rendered["title"]).unwrap(),
+            r#""Fix \"quoted\" title""#
+        );
+    }
+
     #[test]
     fn test_render_template_map() {
         let mut context = Context::new();
@@ -189,4 +403,53 @@ mod tests {
         assert_eq!(result.get("number").unwrap(), "Value: 123");
         assert_eq!(result.get("plain").unwrap(), "No template here");
     }
+
+    #[test]
+    fn test_evaluate_condition() {
+        let mut context = Context::new();
+        context.insert(
+            "event",
+            &json!({"branch": "main", "changed_files": ["a", "b"]}),
+        );
+        insert_step_context(
+            &mut context,
+            "deploy",
+            &[("id".to_string(), "d-1".to_string())]
+                .into_iter()
+                .collect(),
+        );
+
+        assert!(evaluate_condition(r#"event.branch == "main""#, &context).unwrap());
+        assert!(evaluate_condition(r#"{{ event.branch != "main" }}"#, &context).is_ok_and(|c| !c));
+        assert!(evaluate_condition("event.changed_files | length > 1", &context).unwrap());
+        assert!(evaluate_condition("steps.deploy.outputs.id", &context).unwrap());
+        assert!(!evaluate_condition("steps.release", &context).unwrap());
+        assert!(evaluate_condition("event.branch ==", &context).is_err());
+    }
+
+    #[test]
+    fn test_render_items() {
+        let mut context = Context::new();
+        context.insert(
+            "match",
+            &json!({"files": [
+                "services/api/src/main.rs",
+                "services/web/index.js",
+                "services/api/Cargo.toml",
+                "README.md",
+            ]}),
+        );
+        let services = r#"{% for file in match.files %}{% if file is starting_with("services/") %}{{ file | split(pat="/") | nth(n=1) }}
+{% endif %}{% endfor %}"#;
+
+        assert_eq!(
+            render_items(services, &context).unwrap(),
+            vec![json!("api"), json!("web")]
+        );
+        assert_eq!(
+            render_items(r#"[{"name": "api"}, {"name": "web"}]"#, &context).unwrap(),
+            vec![json!({"name": "api"}), json!({"name": "web"})]
+        );
+        assert!(render_items("", &context).unwrap().is_empty());
+    }
 }
@@ -1,16 +1,25 @@
-use super::types::{Branch, Event, EventType, Path, WebhookTypeHandler};
-use crate::app::config::{webhook, Action, Rule};
+use super::projects::{self, RepositoryFiles};
+use super::types::{
+    Author, Branch, EvaluatedRule, Event, EventType, Path, PullRequest, WebhookTypeHandler,
+};
+use crate::app::config::{webhook, webhook::ProjectSpec, Rule};
 use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
 use bitbucket_server_rs::ApiRequest;
+use indexmap::IndexMap;
 use serde_json::Value;
-use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
+
+/// Timeout of a repository file lookup, in seconds
+const FILE_TIMEOUT_SECS: u64 = 10;
 
 pub struct Bitbucket<'a> {
     pub config: webhook::Bitbucket,
-    pub rules: HashMap<String, &'a Rule>,
+    pub rules: IndexMap<String, &'a Rule>,
     pub payload: Value,
+    /// Monorepo project definitions of the webhook
+    pub projects: Vec<ProjectSpec>,
 }
 
 impl Bitbucket<'_> {
@@ -30,6 +39,25 @@ impl Bitbucket<'_> {
             )
     }
 
+    pub async fn extract_pull_request(&self) -> Result<PullRequest> {
+        let pull_request = &self.payload["pullRequest"];
+        let author = &pull_request["author"]["user"];
+        let as_string = |value: &Value| value.as_str().unwrap_or_default().to_string();
+
+        Ok(PullRequest {
+            id: pull_request["id"].as_u64().unwrap_or_default(),
+            commit: as_string(&pull_request["fromRef"]["latestCommit"]),
+            link: as_string(&pull_request["links"]["self"][0]["href"]),
+            title: as_string(&pull_request["title"]),
+            description: as_string(&pull_request["description"]),
+            author: Author {
+                username: as_string(&author["name"]),
+                email: as_string(&author["emailAddress"]),
+                display_name: as_string(&author["displayName"]),
+            },
+        })
+    }
+
     pub async fn extract_changed_files(&self) -> Result<Vec<Path>> {
         // bitbucket api config vars
         let bitbucket_api = &self.config.api.base_url;
@@ -70,6 +98,84 @@ impl Bitbucket<'_> {
 
         Ok(changed_files)
     }
+
+    /// Group the changed files into the webhook's projects
+    pub async fn extract_projects(&self, changed_files: &[Path]) -> Result<Vec<projects::Project>> {
+        if self.projects.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let files = BitbucketFiles::new(&self.config.api, &self.payload)?;
+        projects::detect(&self.projects, changed_files, &files).await
+    }
+}
+
+/// Files of the repository at the pull request's head commit
+struct BitbucketFiles {
+    client: reqwest::Client,
+    raw_url: reqwest::Url,
+    token: String,
+    commit: String,
+}
+
+impl BitbucketFiles {
+    fn new(api: &webhook::BitbucketApi, payload: &Value) -> Result<Self> {
+        let commit = payload["pullRequest"]["fromRef"]["latestCommit"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Missing pull request head commit from payload"))?;
+        let token = env::var(&api.auth.token_from_env).map_err(|_| {
+            anyhow!(
+                "Bitbucket token env var {} is not set",
+                api.auth.token_from_env
+            )
+        })?;
+        let raw_url = format!(
+            "{}/api/latest/projects/{}/repos/{}/raw",
+            api.base_url.trim_end_matches('/'),
+            api.project,
+            api.repo
+        );
+        let raw_url = reqwest::Url::parse(&raw_url)
+            .with_context(|| format!("Invalid Bitbucket API URL: {}", raw_url))?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(FILE_TIMEOUT_SECS))
+            .build()
+            .with_context(|| "Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            raw_url,
+            token,
+            commit: commit.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl RepositoryFiles for BitbucketFiles {
+    /// Check a file at the pull request's head commit
+    async fn file_exists(&self, path: &str) -> Result<bool> {
+        // each path segment is percent-encoded
+        let mut url = self.raw_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Invalid Bitbucket API URL: {}", self.raw_url))?
+            .extend(path.split('/'));
+
+        let response = self
+            .client
+            .head(url)
+            .query(&[("at", &self.commit)])
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .with_context(|| format!("Could not check file {} in bitbucket", path))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status => bail!("Unexpected status checking file {}: {}", path, status),
+        }
+    }
 }
 
 #[async_trait]
@@ -78,23 +184,27 @@ impl WebhookTypeHandler for Bitbucket<'_> {
         let branch = self.extract_branch().await?;
         let changed_files = self.extract_changed_files().await?;
         let event_type = self.extract_event_type().await?;
+        let pull_request = self.extract_pull_request().await?;
+        let projects = self.extract_projects(&changed_files).await?;
 
         //
         Ok(Event {
             event_type,
             branch,
             changed_files,
+            pull_request,
+            projects,
         })
     }
 
-    async fn run(&self) -> Result<Vec<&Action>> {
+    async fn run(&self) -> Result<(Event, Vec<EvaluatedRule<'_>>)> {
         // platform-neutral event
         let event = self.extract_event().await?;
 
         //
-        let actions = Self::evaluate_rules(&event, &self.rules);
+        let evaluated = Self::evaluate_rules(&event, &self.rules);
 
-        Ok(actions)
+        Ok((event, evaluated))
     }
 }
 
@@ -108,6 +218,8 @@ mod tests {
     use super::*;
     use crate::app::config::webhook::{BitbucketApi, BitbucketAuth};
     use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn create_test_bitbucket(payload: Value) -> Bitbucket<'static> {
         Bitbucket {
@@ -123,8 +235,9 @@ mod tests {
                     },
                 },
             },
-            rules: HashMap::new(),
+            rules: IndexMap::new(),
             payload,
+            projects: vec![],
         }
     }
 
@@ -242,6 +355,61 @@ mod tests {
         assert_eq!(branch.unwrap(), "feature/test-push-branch-no-pr");
     }
 
+    #[tokio::test]
+    async fn test_extract_pull_request() {
+        let payload = json!({
+            "pullRequest": {
+                "id": 42,
+                "fromRef": {
+                    "latestCommit": "abc123"
+                },
+                "links": {
+                    "self": [{"href": "https://bitbucket.example.com/projects/PRJ/repos/repo/pull-requests/42"}]
+                },
+                "title": "WIP: add feature",
+                "description": "Adds the feature",
+                "author": {
+                    "user": {
+                        "name": "renovate",
+                        "emailAddress": "renovate@example.com",
+                        "displayName": "Renovate Bot"
+                    }
+                }
+            }
+        });
+
+        let bitbucket = create_test_bitbucket(payload);
+
+        let pull_request = bitbucket.extract_pull_request().await.unwrap();
+        assert_eq!(pull_request.id, 42);
+        assert_eq!(pull_request.commit, "abc123");
+        assert_eq!(
+            pull_request.link,
+            "https://bitbucket.example.com/projects/PRJ/repos/repo/pull-requests/42"
+        );
+        assert_eq!(pull_request.title, "WIP: add feature");
+        assert_eq!(pull_request.description, "Adds the feature");
+        assert_eq!(pull_request.author.username, "renovate");
+        assert_eq!(pull_request.author.email, "renovate@example.com");
+        assert_eq!(pull_request.author.display_name, "Renovate Bot");
+    }
+
+    #[tokio::test]
+    async fn test_extract_pull_request_missing_fields() {
+        let payload = json!({
+            "pullRequest": {
+                "title": "Add feature"
+            }
+        });
+
+        let bitbucket = create_test_bitbucket(payload);
+
+        let pull_request = bitbucket.extract_pull_request().await.unwrap();
+        assert_eq!(pull_request.title, "Add feature");
+        assert_eq!(pull_request.description, "");
+        assert_eq!(pull_request.author.username, "");
+    }
+
     #[tokio::test]
     async fn test_invalid_payload() {
         // A completely invalid payload that doesn't match Bitbucket structure
@@ -265,4 +433,54 @@ mod tests {
         let branch_result = bitbucket.extract_branch().await;
         assert!(branch_result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_file_exists_encodes_path() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path(
+                "/api/latest/projects/PRJ/repos/repo/raw/my%20app/Cargo.toml",
+            ))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        env::set_var("GIT_ACTIONS_TEST_FILES_TOKEN", "token");
+        let api = BitbucketApi {
+            base_url: mock_server.uri(),
+            project: "PRJ".to_string(),
+            repo: "repo".to_string(),
+            auth: BitbucketAuth {
+                auth_type: "token".to_string(),
+                token_from_env: "GIT_ACTIONS_TEST_FILES_TOKEN".to_string(),
+            },
+        };
+        let payload = json!({"pullRequest": {"fromRef": {"latestCommit": "abc123"}}});
+
+        // Execute
+        let files = BitbucketFiles::new(&api, &payload).unwrap();
+        let exists = files.file_exists("my app/Cargo.toml").await;
+
+        // Verify
+        assert!(exists.unwrap());
+    }
+
+    #[test]
+    fn test_files_require_token() {
+        let api = BitbucketApi {
+            base_url: "http://localhost".to_string(),
+            project: "PRJ".to_string(),
+            repo: "repo".to_string(),
+            auth: BitbucketAuth {
+                auth_type: "token".to_string(),
+                token_from_env: "GIT_ACTIONS_TEST_UNSET_TOKEN".to_string(),
+            },
+        };
+        let payload = json!({"pullRequest": {"fromRef": {"latestCommit": "abc123"}}});
+
+        let result = BitbucketFiles::new(&api, &payload);
+
+        assert!(result.is_err());
+    }
 }
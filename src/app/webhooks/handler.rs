@@ -1,11 +1,11 @@
 use crate::app::{
-    config::{
-        rules::{Action, HttpAction, Rule},
-        WebhookConfig,
-    },
+    actions::ActionResult,
+    config::{rules::Rule, server::ExecutionMode, Config, WebhookConfig},
     template,
     webhooks::bitbucket::Bitbucket,
-    webhooks::types::{Event, WebhookTypeHandler},
+    webhooks::schedule,
+    webhooks::types::{EvaluatedRule, Event, WebhookTypeHandler},
+    worker::{exec_job, Job},
     AppState, Error,
     Error::Handler,
 };
@@ -15,14 +15,17 @@ use axum::{
     response::IntoResponse,
     Json,
 };
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::Serialize;
 use serde_json::{json, Value};
-use std::collections::HashMap;
 use std::sync::Arc;
-use tera::Context;
-use tracing::{debug, error};
+use tracing::{debug, info, instrument, warn, Span};
+use uuid::Uuid;
 use Error::{RulesNotFoundForWebhook, WebhookNotFoundForPath};
 
 #[axum::debug_handler]
+#[instrument(skip_all, fields(path = %path, run_id = tracing::field::Empty))]
 pub async fn handler(
     Path(path): Path<String>,
     State(state): State<Arc<AppState>>,
@@ -54,33 +57,199 @@ pub async fn handler(
     // TODO - use a factory to create the handler based on the webhook type
     let handler = create_bitbucket_handler(payload, webhook_config.to_owned(), webhook_rules)?;
 
-    // run the webhook handler
-    let actions = handler.run().await.map_err(|e| Handler(e.to_string()))?;
-    debug!("Handler actions: {:?}", actions);
-
-    // Extract the event for template rendering
-    let event = handler
-        .extract_event()
-        .await
-        .map_err(|e| Handler(e.to_string()))?;
-
-    // exec the actions with the event for template context
-    exec_actions(actions, &event).await?;
-
-    // Return a success response
-    Ok((
-        StatusCode::OK,
-        Json(json!({
-            "message": format!("Webhook processed: {}", &webhook_config.metadata.name),
-        })),
+    // run the webhook handler, the event is also used for template rendering
+    let (event, evaluated) = handler.run().await.map_err(|e| Handler(e.to_string()))?;
+    debug!("Handler evaluated rules: {:?}", evaluated);
+
+    // prepare the actions of the matched rules with the event for template context
+    let run_id = Uuid::new_v4().to_string();
+    Span::current().record("run_id", run_id.as_str());
+    let (mut rules, jobs) = prepare_jobs(
+        &evaluated,
+        &event,
+        webhook_config,
+        &state.config,
+        &run_id,
+        Utc::now(),
+    );
+    let name = &webhook_config.metadata.name;
+    info!(
+        webhook = %name,
+        matched = rules.iter().filter(|rule| rule.matched).count(),
+        jobs = jobs.len(),
+        "Webhook rules evaluated"
+    );
+
+    // queue the actions and respond before they run
+    let async_workers = state
+        .workers
+        .as_ref()
+        .filter(|_| state.mode == ExecutionMode::Async);
+    if let Some(workers) = async_workers {
+        workers.submit(jobs).await?;
+        if rules.iter().all(|rule| rule.status != RuleStatus::Failed) {
+            return Ok((
+                StatusCode::ACCEPTED,
+                Json(json!({
+                    "message": format!("Webhook accepted: {}", name),
+                    "run_id": run_id,
+                    "rules": rules,
+                })),
+            ));
+        }
+
+        // some rules failed before their actions could be queued
+        let run_rules = rules
+            .iter()
+            .filter(|rule| {
+                matches!(
+                    rule.status,
+                    RuleStatus::Queued | RuleStatus::Scheduled | RuleStatus::Failed
+                )
+            })
+            .count();
+        return Ok(respond(
+            name,
+            &run_id,
+            &rules,
+            run_rules,
+            state.partial_failure_status,
+        ));
+    }
+
+    // the actions waiting for their rule's schedule to open are left to the workers
+    let (scheduled, jobs): (Vec<_>, Vec<_>) =
+        jobs.into_iter().partition(|job| job.not_before.is_some());
+    if !scheduled.is_empty() {
+        state
+            .workers
+            .as_ref()
+            .ok_or_else(|| Handler("no action workers to queue scheduled actions".to_string()))?
+            .submit(scheduled)
+            .await?;
+    }
+
+    // run the rules in order, a failed rule does not stop the others
+    for job in jobs {
+        let Some(rule) = rules
+            .iter_mut()
+            .find(|rule| rule.job_id.as_deref() == Some(job.id.as_str()))
+        else {
+            continue;
+        };
+
+        match exec_job(&job, state.dead_letters.as_deref()).await {
+            Ok(results) => {
+                rule.status = RuleStatus::Succeeded;
+                rule.actions = results;
+            }
+            Err(failed) => {
+                rule.status = RuleStatus::Failed;
+                rule.actions = failed.results;
+                rule.error = Some(failed.error.to_string());
+            }
+        }
+    }
+
+    let run_rules = rules
+        .iter()
+        .filter(|rule| matches!(rule.status, RuleStatus::Succeeded | RuleStatus::Failed))
+        .count();
+    Ok(respond(
+        name,
+        &run_id,
+        &rules,
+        run_rules,
+        state.partial_failure_status,
     ))
 }
 
+/// Response reporting the rules of a delivery, with a status depending on how
+/// many of the `run_rules` failed
+fn respond(
+    name: &str,
+    run_id: &str,
+    rules: &[RuleResult],
+    run_rules: usize,
+    partial_failure_status: StatusCode,
+) -> (StatusCode, Json<Value>) {
+    let failed_rules = rules
+        .iter()
+        .filter(|rule| rule.status == RuleStatus::Failed)
+        .count();
+    let (status, outcome) = response_status(run_rules, failed_rules, partial_failure_status);
+    let mut body = json!({
+        "message": format!("Webhook {}: {}", outcome, name),
+        "run_id": run_id,
+        "rules": rules,
+    });
+    if failed_rules > 0 {
+        body["error"] = json!(format!("{} of {} rules failed", failed_rules, run_rules));
+    }
+
+    (status, Json(body))
+}
+
+/// Response status and outcome of a webhook delivery given the number of rules
+/// run and failed. Only some of the rules failing is a partial failure
+fn response_status(
+    run_rules: usize,
+    failed_rules: usize,
+    partial_failure_status: StatusCode,
+) -> (StatusCode, &'static str) {
+    if failed_rules == 0 {
+        (StatusCode::OK, "processed")
+    } else if failed_rules < run_rules {
+        (partial_failure_status, "partially processed")
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, "failed")
+    }
+}
+
+/// Outcome of an evaluated rule, reported in the webhook response. A rule with
+/// a `foreach` has one result per item
+#[derive(Clone, Debug, Serialize)]
+struct RuleResult {
+    rule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    item: Option<Value>,
+    matched: bool,
+    status: RuleStatus,
+    /// Why the rule did not match or was blocked by its schedule
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// Results of the actions run, the failed one last
+    actions: Vec<ActionResult>,
+    /// Why the rule failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Job running the rule's actions
+    #[serde(skip)]
+    job_id: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RuleStatus {
+    /// The rule did not match the event
+    NotMatched,
+    /// The rule's schedule is closed, its actions were dropped
+    Blocked,
+    /// The rule's schedule is closed, its actions run when it opens
+    Scheduled,
+    /// The actions are waiting for a worker
+    Queued,
+    Succeeded,
+    Failed,
+    /// The rule's foreach has no items
+    NoItems,
+}
+
 fn create_bitbucket_handler(
     payload: Value,
     webhook_config: WebhookConfig,
-    webhook_rules: HashMap<String, &Rule>,
-) -> Result<Bitbucket, Error> {
+    webhook_rules: IndexMap<String, &Rule>,
+) -> Result<Bitbucket<'_>, Error> {
     let bitbucket_config = webhook_config.spec.bitbucket.as_ref().ok_or_else(|| {
         Error::WebhookConfig(format!(
             "Bitbucket config is missing for webhook: {}",
@@ -93,79 +262,413 @@ fn create_bitbucket_handler(
         config: bitbucket_config.to_owned(),
         rules: webhook_rules,
         payload,
+        projects: webhook_config.spec.projects.clone().unwrap_or_default(),
     };
     Ok(handler)
 }
 
-// TODO refactor to separate module?
-async fn exec_actions(actions: Vec<&Action>, event: &Event) -> Result<(), Error> {
+/// Build the jobs running the actions of the matched rules, and the result of
+/// each evaluated rule. The jobs of rules blocked by their schedule at `now`
+/// wait until the schedule opens, or are skipped
+fn prepare_jobs(
+    evaluated: &[EvaluatedRule<'_>],
+    event: &Event,
+    webhook: &WebhookConfig,
+    config: &Config,
+    run_id: &str,
+    now: DateTime<Utc>,
+) -> (Vec<RuleResult>, Vec<Job>) {
     // Build the template context once with all environment variables
-    let context = template::build_template_context(event);
+    let mut base_context = template::build_template_context(event);
+    template::insert_webhook_context(&mut base_context, webhook);
+    let mut rules = Vec::new();
+    let mut jobs = Vec::new();
+
+    for evaluated_rule in evaluated {
+        let mut result = RuleResult {
+            rule: evaluated_rule.name.clone(),
+            item: None,
+            matched: evaluated_rule.evaluation.matched,
+            status: RuleStatus::NotMatched,
+            reason: evaluated_rule.evaluation.reason.clone(),
+            actions: Vec::new(),
+            error: None,
+            job_id: None,
+        };
+        if !result.matched {
+            rules.push(result);
+            continue;
+        }
 
-    println!("{:?}", context);
+        // each rule's actions see the files matched by that rule
+        let mut context = base_context.clone();
+        template::insert_match_context(&mut context, &evaluated_rule.evaluation);
 
-    for action in actions {
-        if let Some(http) = &action.http {
-            // TODO tracing
-            exec_http_action(http, &context).await?;
+        // a rule with a foreach runs its actions once per item
+        let items = match &evaluated_rule.rule.foreach {
+            Some(foreach) => match template::render_items(foreach, &context) {
+                Ok(items) => items.into_iter().map(Some).collect(),
+                Err(e) => {
+                    warn!("Rule {} foreach failed: {}", evaluated_rule.name, e);
+                    result.status = RuleStatus::Failed;
+                    result.error = Some(format!("Failed to render foreach {}: {}", foreach, e));
+                    rules.push(result);
+                    continue;
+                }
+            },
+            None => vec![None],
+        };
+        if items.is_empty() {
+            result.status = RuleStatus::NoItems;
+            rules.push(result);
+            continue;
         }
-        if let Some(_shell) = &action.shell {
-            // TODO implement shell action
-            error!("Shell action is not implemented yet");
+        let contexts: Vec<_> = items
+            .into_iter()
+            .map(|item| {
+                let mut context = context.clone();
+                if let Some(item) = &item {
+                    context.insert("item", item);
+                }
+                (item, context)
+            })
+            .collect();
+
+        // the schedule decides whether the matched rule's actions run now
+        if let Some(rule_schedule) = &evaluated_rule.rule.schedule {
+            let freezes = rule_schedule.freezes.as_deref().unwrap_or_default();
+            let calendars = match config.find_freeze_calendars(freezes) {
+                Ok(calendars) => calendars,
+                Err(e) => {
+                    warn!("Rule {} schedule failed: {}", evaluated_rule.name, e);
+                    result.status = RuleStatus::Failed;
+                    result.error = Some(format!("Failed to find freeze calendars: {}", e));
+                    rules.push(result);
+                    continue;
+                }
+            };
+
+            if let Err(blocked) = schedule::check(rule_schedule, &calendars, now) {
+                info!(
+                    "Rule {} blocked by schedule: {}",
+                    evaluated_rule.name, blocked.reason
+                );
+                result.status = RuleStatus::Blocked;
+                result.reason = Some(blocked.reason);
+
+                if rule_schedule.queue {
+                    match blocked.opens_at {
+                        Some(opens_at) => {
+                            info!(
+                                "Rule {} actions queued until {}",
+                                evaluated_rule.name, opens_at
+                            );
+                            result.status = RuleStatus::Scheduled;
+                            for (item, context) in contexts {
+                                let job = Job {
+                                    id: Uuid::new_v4().to_string(),
+                                    run_id: run_id.to_string(),
+                                    rule_name: evaluated_rule.name.clone(),
+                                    rule: evaluated_rule.rule.clone(),
+                                    context,
+                                    not_before: Some(opens_at),
+                                };
+                                rules.push(RuleResult {
+                                    item,
+                                    job_id: Some(job.id.clone()),
+                                    ..result.clone()
+                                });
+                                jobs.push(job);
+                            }
+                            continue;
+                        }
+                        None => warn!(
+                            "Rule {} schedule does not open soon enough, actions dropped",
+                            evaluated_rule.name
+                        ),
+                    }
+                }
+                rules.push(result);
+                continue;
+            }
+        }
+
+        for (item, context) in contexts {
+            let job = Job {
+                id: Uuid::new_v4().to_string(),
+                run_id: run_id.to_string(),
+                rule_name: evaluated_rule.name.clone(),
+                rule: evaluated_rule.rule.clone(),
+                context,
+                not_before: None,
+            };
+            rules.push(RuleResult {
+                item,
+                status: RuleStatus::Queued,
+                job_id: Some(job.id.clone()),
+                ..result.clone()
+            });
+            jobs.push(job);
         }
     }
 
-    // TODO return some details about the action
-    Ok(())
+    (rules, jobs)
 }
 
-// TODO refactor to separate module?
-async fn exec_http_action(action: &HttpAction, context: &Context) -> Result<(), Error> {
-    // create a new HTTP client
-    let client = reqwest::Client::new();
-
-    // Render templates in method and url
-    let method = template::render_template(&action.method, context)
-        .map_err(|e| Error::Action(format!("Failed to render method template: {}", e)))?;
-
-    let url = template::render_template(&action.url, context)
-        .map_err(|e| Error::Action(format!("Failed to render URL template: {}", e)))?;
-
-    // set request method
-    let mut client = match method.to_uppercase().as_str() {
-        "GET" => client.get(&url),
-        "POST" => client.post(&url),
-        _ => {
-            return Err(Error::Action(format!(
-                "Unsupported HTTP method: {}",
-                method
-            )))
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::rules::{Schedule, TimeWindow};
+    use crate::app::webhooks::{
+        rule_evaluator::Evaluation,
+        types::{EventType, PullRequest},
     };
+    use chrono::{TimeZone, Weekday};
 
-    // headers with template rendering
-    if let Some(headers) = &action.headers {
-        let rendered_headers = template::render_template_map(headers, context);
-        for (key, value) in rendered_headers.iter() {
-            client = client.header(key, value);
-        }
+    fn create_test_webhook() -> WebhookConfig {
+        serde_yaml::from_str(
+            r#"
+apiVersion: v1
+kind: Webhook
+metadata:
+  name: bitbucket
+spec:
+  path: bitbucket
+"#,
+        )
+        .unwrap()
     }
 
-    // body with template rendering
-    if let Some(body) = &action.body {
-        let rendered_body = template::render_template(body, context)
-            .map_err(|e| Error::Action(format!("Failed to render body template: {}", e)));
+    #[test]
+    fn test_prepare_jobs_reports_each_rule() {
+        // Setup
+        let event = Event {
+            event_type: EventType::Opened,
+            branch: "main".to_string(),
+            changed_files: vec![],
+            pull_request: PullRequest::default(),
+            projects: vec![],
+        };
+        let rule = Rule::default();
+        let evaluated = vec![
+            EvaluatedRule {
+                name: "deploy".to_string(),
+                rule: &rule,
+                evaluation: Evaluation {
+                    matched: true,
+                    ..Default::default()
+                },
+            },
+            EvaluatedRule {
+                name: "release".to_string(),
+                rule: &rule,
+                evaluation: Evaluation::failed("branch does not match".to_string()),
+            },
+        ];
 
-        client = client.body(rendered_body?);
+        // Execute
+        let (rules, jobs) = prepare_jobs(
+            &evaluated,
+            &event,
+            &create_test_webhook(),
+            &Config::new(),
+            "run-1",
+            Utc::now(),
+        );
+
+        // Verify
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].rule_name, "deploy");
+        assert_eq!(jobs[0].run_id, "run-1");
+        assert_eq!(jobs[0].context.get("webhook").unwrap()["name"], "bitbucket");
+        assert_eq!(rules[0].status, RuleStatus::Queued);
+        assert_eq!(rules[1].status, RuleStatus::NotMatched);
+        assert_eq!(rules[1].reason.as_deref(), Some("branch does not match"));
+        assert_eq!(
+            serde_json::to_value(&rules[1]).unwrap(),
+            json!({
+                "rule": "release",
+                "matched": false,
+                "status": "not_matched",
+                "reason": "branch does not match",
+                "actions": [],
+            })
+        );
     }
 
-    // send the request
-    let response = client
-        .send()
-        .await
-        .map_err(|e| Error::Action(e.to_string()))?;
+    #[test]
+    fn test_response_status() {
+        let partial = StatusCode::MULTI_STATUS;
 
-    debug!("Action status: {}", response.status());
+        assert_eq!(
+            response_status(0, 0, partial),
+            (StatusCode::OK, "processed")
+        );
+        assert_eq!(
+            response_status(2, 0, partial),
+            (StatusCode::OK, "processed")
+        );
+        assert_eq!(
+            response_status(2, 1, partial),
+            (StatusCode::MULTI_STATUS, "partially processed")
+        );
+        assert_eq!(
+            response_status(2, 2, partial),
+            (StatusCode::INTERNAL_SERVER_ERROR, "failed")
+        );
+    }
+
+    #[test]
+    fn test_prepare_jobs_runs_rule_per_item() {
+        // Setup
+        let event = Event {
+            event_type: EventType::Opened,
+            branch: "main".to_string(),
+            changed_files: vec![
+                "services/api/main.rs".to_string(),
+                "services/web/index.js".to_string(),
+            ],
+            pull_request: PullRequest::default(),
+            projects: vec![],
+        };
+        let rule = Rule {
+            foreach: Some(
+                r#"{% for file in event.changed_files %}{{ file | split(pat="/") | nth(n=1) }}
+{% endfor %}"#
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let evaluated = vec![EvaluatedRule {
+            name: "build".to_string(),
+            rule: &rule,
+            evaluation: Evaluation {
+                matched: true,
+                ..Default::default()
+            },
+        }];
 
-    Ok(())
+        // Execute
+        let (rules, jobs) = prepare_jobs(
+            &evaluated,
+            &event,
+            &create_test_webhook(),
+            &Config::new(),
+            "run-1",
+            Utc::now(),
+        );
+
+        // Verify
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[1].context.get("item"), Some(&json!("web")));
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].item, Some(json!("api")));
+        assert_eq!(rules[0].job_id.as_deref(), Some(jobs[0].id.as_str()));
+    }
+
+    #[test]
+    fn test_prepare_jobs_delays_scheduled_rule() {
+        // Setup: blocked for the whole of Friday
+        let now = Utc.with_ymd_and_hms(2025, 10, 17, 12, 0, 0).unwrap();
+        let event = Event {
+            event_type: EventType::Opened,
+            branch: "main".to_string(),
+            changed_files: vec![],
+            pull_request: PullRequest::default(),
+            projects: vec![],
+        };
+        let rule = Rule {
+            schedule: Some(Schedule {
+                block: Some(vec![TimeWindow {
+                    days: Some(vec![Weekday::Fri]),
+                    ..Default::default()
+                }]),
+                queue: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let evaluated = vec![EvaluatedRule {
+            name: "deploy".to_string(),
+            rule: &rule,
+            evaluation: Evaluation {
+                matched: true,
+                ..Default::default()
+            },
+        }];
+
+        // Execute
+        let (rules, jobs) = prepare_jobs(
+            &evaluated,
+            &event,
+            &create_test_webhook(),
+            &Config::new(),
+            "run-1",
+            now,
+        );
+
+        // Verify
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(
+            jobs[0].not_before,
+            Some(Utc.with_ymd_and_hms(2025, 10, 18, 0, 0, 0).unwrap())
+        );
+        assert_eq!(rules[0].status, RuleStatus::Scheduled);
+        assert_eq!(rules[0].job_id.as_deref(), Some(jobs[0].id.as_str()));
+    }
+
+    #[test]
+    fn test_prepare_jobs_fails_rule_with_unknown_freeze() {
+        // Setup
+        let event = Event {
+            event_type: EventType::Opened,
+            branch: "main".to_string(),
+            changed_files: vec![],
+            pull_request: PullRequest::default(),
+            projects: vec![],
+        };
+        let frozen = Rule {
+            schedule: Some(Schedule {
+                freezes: Some(vec!["missing".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let rule = Rule::default();
+        let evaluated = vec![
+            EvaluatedRule {
+                name: "frozen".to_string(),
+                rule: &frozen,
+                evaluation: Evaluation {
+                    matched: true,
+                    ..Default::default()
+                },
+            },
+            EvaluatedRule {
+                name: "build".to_string(),
+                rule: &rule,
+                evaluation: Evaluation {
+                    matched: true,
+                    ..Default::default()
+                },
+            },
+        ];
+
+        // Execute
+        let (rules, jobs) = prepare_jobs(
+            &evaluated,
+            &event,
+            &create_test_webhook(),
+            &Config::new(),
+            "run-1",
+            Utc::now(),
+        );
+
+        // Verify
+        assert_eq!(rules[0].status, RuleStatus::Failed);
+        assert!(rules[0].error.as_deref().unwrap().contains("missing"));
+        assert_eq!(rules[1].status, RuleStatus::Queued);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].rule_name, "build");
+    }
 }
@@ -1,5 +1,7 @@
 pub mod bitbucket;
+pub mod projects;
 pub mod rule_evaluator;
+pub mod schedule;
 pub mod types;
 
 pub(crate) mod handler;
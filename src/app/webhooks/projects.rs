@@ -0,0 +1,217 @@
+use crate::app::config::webhook::ProjectSpec;
+use crate::app::webhooks::types::Path;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use glob::Pattern;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, error};
+
+/// Project of a monorepo with its changed files
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Project {
+    pub name: String,
+    /// Directory of a marker project, empty for a project defined by globs
+    pub path: String,
+    pub files: Vec<Path>,
+}
+
+/// Lookup of the files of the repository at the event's commit
+#[async_trait]
+pub trait RepositoryFiles {
+    async fn file_exists(&self, path: &str) -> Result<bool>;
+}
+
+/// Marker file lookups running at the same time
+const MAX_CONCURRENT_LOOKUPS: usize = 8;
+
+/// Group the changed files into projects, in declaration order of the project
+/// definitions. A file belongs to the first definition it matches; files outside
+/// of any project are left out
+pub async fn detect(
+    specs: &[ProjectSpec],
+    changed_files: &[Path],
+    repository: &(dyn RepositoryFiles + Sync),
+) -> Result<Vec<Project>> {
+    // project name and path of each file, trying the definitions in order
+    let mut assigned: HashMap<&str, (String, String)> = HashMap::new();
+    for spec in specs {
+        let unassigned: Vec<&str> = changed_files
+            .iter()
+            .map(String::as_str)
+            .filter(|file| !assigned.contains_key(file))
+            .collect();
+
+        match spec {
+            ProjectSpec::Paths { name, paths } => {
+                for file in unassigned {
+                    if paths.iter().any(|pattern| matches_glob(pattern, file)) {
+                        assigned.insert(file, (name.clone(), String::new()));
+                    }
+                }
+            }
+            ProjectSpec::Marker { marker } => {
+                for (file, dir) in find_marker_dirs(marker, &unassigned, repository).await? {
+                    let name = if dir.is_empty() {
+                        ".".to_string()
+                    } else {
+                        dir.clone()
+                    };
+                    assigned.insert(file, (name, dir));
+                }
+            }
+        }
+    }
+
+    let mut projects: Vec<Project> = Vec::new();
+    for file in changed_files {
+        let Some((name, path)) = assigned.remove(file.as_str()) else {
+            continue;
+        };
+        debug!("Changed file {} belongs to project {}", file, name);
+        match projects.iter_mut().find(|project| project.name == name) {
+            Some(project) => project.files.push(file.clone()),
+            None => projects.push(Project {
+                name,
+                path,
+                files: vec![file.clone()],
+            }),
+        }
+    }
+
+    Ok(projects)
+}
+
+/// Closest directory of each file holding the marker file, the repository root
+/// being the empty path. Files without one are left out. The directories are
+/// looked up one level at a time, the lookups of a level running concurrently
+async fn find_marker_dirs<'a>(
+    marker: &str,
+    files: &[&'a str],
+    repository: &(dyn RepositoryFiles + Sync),
+) -> Result<HashMap<&'a str, String>> {
+    let marker_path = |dir: &str| {
+        if dir.is_empty() {
+            marker.to_string()
+        } else {
+            format!("{}/{}", dir, marker)
+        }
+    };
+    let mut markers: HashMap<String, bool> = HashMap::new();
+    let mut found = HashMap::new();
+    let mut pending: Vec<(&str, &str)> = files
+        .iter()
+        .filter_map(|file| parent_dir(file).map(|dir| (*file, dir)))
+        .collect();
+
+    while !pending.is_empty() {
+        let lookups: HashSet<String> = pending
+            .iter()
+            .map(|(_, dir)| marker_path(dir))
+            .filter(|path| !markers.contains_key(path))
+            .collect();
+        let exists: Vec<(String, bool)> = stream::iter(lookups)
+            .map(|path| async move {
+                let exists = repository.file_exists(&path).await?;
+                Ok::<_, anyhow::Error>((path, exists))
+            })
+            .buffer_unordered(MAX_CONCURRENT_LOOKUPS)
+            .try_collect()
+            .await?;
+        markers.extend(exists);
+
+        let mut next = Vec::new();
+        for (file, dir) in pending {
+            if markers.get(&marker_path(dir)).copied().unwrap_or_default() {
+                found.insert(file, dir.to_string());
+            } else if let Some(parent) = parent_dir(dir) {
+                next.push((file, parent));
+            }
+        }
+        pending = next;
+    }
+
+    Ok(found)
+}
+
+/// Parent directory of a path, the repository root being the empty path
+fn parent_dir(path: &str) -> Option<&str> {
+    if path.is_empty() {
+        return None;
+    }
+    Some(path.rsplit_once('/').map_or("", |(parent, _)| parent))
+}
+
+fn matches_glob(pattern: &str, file: &str) -> bool {
+    match Pattern::new(pattern) {
+        Ok(pattern) => pattern.matches(file),
+        Err(_) => {
+            error!("Invalid project pattern: {}", pattern);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct TestRepository {
+        files: HashSet<&'static str>,
+        lookups: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl RepositoryFiles for TestRepository {
+        async fn file_exists(&self, path: &str) -> Result<bool> {
+            *self.lookups.lock().unwrap() += 1;
+            Ok(self.files.contains(path))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_detect_projects() {
+        // Setup
+        let specs = vec![
+            ProjectSpec::Paths {
+                name: "docs".to_string(),
+                paths: vec!["docs/**".to_string(), "*.md".to_string()],
+            },
+            ProjectSpec::Marker {
+                marker: "Cargo.toml".to_string(),
+            },
+        ];
+        let repository = TestRepository {
+            files: ["services/api/Cargo.toml", "services/worker/Cargo.toml"]
+                .into_iter()
+                .collect(),
+            lookups: Mutex::new(0),
+        };
+        let changed_files: Vec<Path> = [
+            "README.md",
+            "services/api/src/main.rs",
+            "services/api/src/lib.rs",
+            "services/worker/Cargo.toml",
+            "scripts/build.sh",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        // Execute
+        let projects = detect(&specs, &changed_files, &repository).await.unwrap();
+
+        // Verify
+        let names: Vec<_> = projects.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["docs", "services/api", "services/worker"]);
+        assert_eq!(
+            projects[1].files,
+            vec!["services/api/src/main.rs", "services/api/src/lib.rs"]
+        );
+        assert_eq!(projects[1].path, "services/api");
+        // lookups are cached per directory
+        assert_eq!(*repository.lookups.lock().unwrap(), 5);
+    }
+}
@@ -1,69 +1,209 @@
 use crate::app::config::{
-    rules::{BranchFilter, PathFilter},
+    rules::{AuthorFilter, BranchFilter, PathFilter, TextFilter},
     Rule,
 };
-use crate::app::webhooks::types::{Branch, Event, EventType, Path};
+use crate::app::webhooks::projects::Project;
+use crate::app::webhooks::types::{Author, Branch, Event, EventType, Path};
 use glob::Pattern;
 use regex::Regex;
+use serde::Serialize;
 use tracing::{debug, error};
 use wildmatch::WildMatch;
 
-pub fn check(event: &Event, rule: &Rule) -> bool {
-    // check event type
-    let result = check_event_type(&event.event_type, &rule.event_types);
-    if !result {
-        debug!("Event type eval FAILED: {}", event.event_type.to_string());
-        return false;
-    } else {
-        debug!("Event type eval OK: {}", event.event_type.to_string());
+/// Trace of a rule evaluated against an event
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Evaluation {
+    /// Whether the rule matched the event
+    pub matched: bool,
+
+    /// Event type filter that matched, if the rule filters on event types
+    pub event_type: Option<String>,
+
+    /// Branch filter that matched, if the rule filters on branches
+    pub branch: Option<BranchFilter>,
+
+    /// Pull request title filter that matched, if the rule filters on the title
+    pub title: Option<TextFilter>,
+
+    /// Pull request description filter that matched, if the rule filters on the description
+    pub description: Option<TextFilter>,
+
+    /// Pull request author filter that matched, if the rule filters on authors
+    pub author: Option<AuthorFilter>,
+
+    /// Path filters that matched at least one changed file
+    pub paths: Vec<PathFilter>,
+
+    /// Changed files matched by the path filters, or all changed files if the rule has none
+    pub files: Vec<Path>,
+
+    /// Names of the projects matched by the project filters, or of all the
+    /// event's projects if the rule has none
+    pub projects: Vec<String>,
+
+    /// Why the rule did not match
+    pub reason: Option<String>,
+}
+
+impl Evaluation {
+    pub(crate) fn failed(reason: String) -> Self {
+        Self {
+            reason: Some(reason),
+            ..Default::default()
+        }
     }
+}
 
-    // check branch
-    let result = check_branch(&event.branch, &rule.branches);
-    if !result {
-        debug!("Branch eval FAILED: {}", event.branch);
-        return false;
-    } else {
-        debug!("Branch eval OK: {}", event.branch);
+pub fn check(event: &Event, rule: &Rule) -> Evaluation {
+    // disabled rules never match
+    if !rule.is_enabled() {
+        debug!("Rule is disabled");
+        return Evaluation::failed("rule is disabled".to_string());
     }
 
+    // check event type
+    let event_type = match check_event_type(&event.event_type, &rule.event_types) {
+        Ok(event_type) => {
+            debug!("Event type eval OK: {}", event.event_type.to_string());
+            event_type
+        }
+        Err(reason) => {
+            debug!("Event type eval FAILED: {}", event.event_type.to_string());
+            return Evaluation::failed(reason);
+        }
+    };
+
+    // check branch
+    let branch = match check_branch(&event.branch, &rule.branches) {
+        Ok(branch) => {
+            debug!("Branch eval OK: {}", event.branch);
+            branch
+        }
+        Err(reason) => {
+            debug!("Branch eval FAILED: {}", event.branch);
+            return Evaluation::failed(reason);
+        }
+    };
+
+    // check pull request title, description and author
+    let pr_filter = rule.pull_request.as_ref();
+    let title = match check_text(
+        "title",
+        &event.pull_request.title,
+        pr_filter.and_then(|f| f.title.as_ref()),
+    ) {
+        Ok(title) => {
+            debug!("Title eval OK: {}", event.pull_request.title);
+            title
+        }
+        Err(reason) => {
+            debug!("Title eval FAILED: {}", event.pull_request.title);
+            return Evaluation::failed(reason);
+        }
+    };
+
+    let description = match check_text(
+        "description",
+        &event.pull_request.description,
+        pr_filter.and_then(|f| f.description.as_ref()),
+    ) {
+        Ok(description) => {
+            debug!("Description eval OK");
+            description
+        }
+        Err(reason) => {
+            debug!("Description eval FAILED");
+            return Evaluation::failed(reason);
+        }
+    };
+
+    let author = match check_author(
+        &event.pull_request.author,
+        pr_filter.and_then(|f| f.authors.as_ref()),
+    ) {
+        Ok(author) => {
+            debug!("Author eval OK: {}", event.pull_request.author.username);
+            author
+        }
+        Err(reason) => {
+            debug!("Author eval FAILED: {}", event.pull_request.author.username);
+            return Evaluation::failed(reason);
+        }
+    };
+
     // check paths / changed files
-    let result = check_changed_files(&event.changed_files, &rule.paths);
-    if !result {
-        debug!("Changed files eval FAILED: {:?}", event.changed_files);
-        return false;
-    } else {
-        debug!("Changed files eval OK: {:?}", event.changed_files);
-    }
+    let (paths, files) = match check_changed_files(&event.changed_files, &rule.paths) {
+        Ok(matched) => {
+            debug!("Changed files eval OK: {:?}", matched.1);
+            matched
+        }
+        Err(reason) => {
+            debug!("Changed files eval FAILED: {:?}", event.changed_files);
+            return Evaluation::failed(reason);
+        }
+    };
 
-    true
+    // check monorepo projects
+    let projects = match check_projects(&event.projects, &rule.projects) {
+        Ok(projects) => {
+            debug!("Projects eval OK: {:?}", projects);
+            projects
+        }
+        Err(reason) => {
+            debug!("Projects eval FAILED");
+            return Evaluation::failed(reason);
+        }
+    };
+
+    Evaluation {
+        matched: true,
+        event_type,
+        branch,
+        title,
+        description,
+        author,
+        paths,
+        files,
+        projects,
+        reason: None,
+    }
 }
 
-fn check_event_type(event_type: &EventType, rule_event_types: &Option<Vec<String>>) -> bool {
+/// Returns the matched event type filter, or `None` if the rule accepts any event type
+fn check_event_type(
+    event_type: &EventType,
+    rule_event_types: &Option<Vec<String>>,
+) -> Result<Option<String>, String> {
     let rule_event_types = match rule_event_types {
         // if None or empty, then it matches any event type
-        None => return true,
-        Some(event_types) if event_types.is_empty() => return true,
+        None => return Ok(None),
+        Some(event_types) if event_types.is_empty() => return Ok(None),
         Some(event_types) => event_types,
     };
 
     // check each one
     for rule_event_type in rule_event_types {
         if &event_type.to_string() == rule_event_type {
-            return true;
+            return Ok(Some(rule_event_type.clone()));
         }
     }
 
-    false
+    Err(format!(
+        "event type {} not in {:?}",
+        event_type, rule_event_types
+    ))
 }
 
 // TODO for PRs, a rule could be matching by source / target branch
-// TODO return matched branch name instead of bool
-fn check_branch(event_branch: &Branch, rule_branches: &Option<Vec<BranchFilter>>) -> bool {
+/// Returns the matched branch filter, or `None` if the rule accepts any branch
+fn check_branch(
+    event_branch: &Branch,
+    rule_branches: &Option<Vec<BranchFilter>>,
+) -> Result<Option<BranchFilter>, String> {
     let rule_branches = match rule_branches {
         // if None or empty, then it matches any branch
-        None => return true,
-        Some(rule_branches) if rule_branches.is_empty() => return true,
+        None => return Ok(None),
+        Some(rule_branches) if rule_branches.is_empty() => return Ok(None),
         Some(rule_branches) => rule_branches,
     };
 
@@ -73,14 +213,14 @@ fn check_branch(event_branch: &Branch, rule_branches: &Option<Vec<BranchFilter>>
             BranchFilter::Exact { exact } => {
                 if exact == event_branch {
                     debug!("Branch matches exact: {}", exact);
-                    return true;
+                    return Ok(Some(branch_filter.clone()));
                 }
             }
             BranchFilter::Pattern { pattern } => {
                 let result = WildMatch::new(pattern).matches(event_branch);
                 if result {
                     debug!("Branch matches wildcard: {}", pattern);
-                    return true;
+                    return Ok(Some(branch_filter.clone()));
                 }
             }
             BranchFilter::Regex { regex } => {
@@ -94,62 +234,218 @@ fn check_branch(event_branch: &Branch, rule_branches: &Option<Vec<BranchFilter>>
                 };
                 if regex.is_match(event_branch) {
                     debug!("Branch matches regex: {}", regex);
-                    return true;
+                    return Ok(Some(branch_filter.clone()));
                 }
             }
         }
     }
 
-    false
+    Err(format!("branch {} matched no branch filter", event_branch))
+}
+
+/// Returns the matched text filter, or `None` if the rule has no positive filters.
+/// Every negated filter must hold and, if there are positive filters, one must match.
+fn check_text(
+    field: &str,
+    value: &str,
+    rule_filters: Option<&Vec<TextFilter>>,
+) -> Result<Option<TextFilter>, String> {
+    let rule_filters = match rule_filters {
+        // if None or empty, then it matches any text
+        None => return Ok(None),
+        Some(rule_filters) if rule_filters.is_empty() => return Ok(None),
+        Some(rule_filters) => rule_filters,
+    };
+
+    // negated filters veto the match
+    for text_filter in rule_filters {
+        if let TextFilter::Not { .. } = text_filter {
+            if !text_matches(value, text_filter) {
+                return Err(format!(
+                    "{} matched negated filter {:?}",
+                    field, text_filter
+                ));
+            }
+        }
+    }
+
+    let mut positive_filters = rule_filters
+        .iter()
+        .filter(|f| !matches!(f, TextFilter::Not { .. }))
+        .peekable();
+    if positive_filters.peek().is_none() {
+        return Ok(None);
+    }
+
+    for text_filter in positive_filters {
+        if text_matches(value, text_filter) {
+            debug!("{} matches: {:?}", field, text_filter);
+            return Ok(Some(text_filter.clone()));
+        }
+    }
+
+    Err(format!("{} matched no {} filter", field, field))
 }
 
-// TODO return matched paths instead of bool
-fn check_changed_files(event_paths: &Vec<Path>, rule_paths: &Option<Vec<PathFilter>>) -> bool {
+fn text_matches(value: &str, text_filter: &TextFilter) -> bool {
+    match text_filter {
+        TextFilter::Exact { exact } => exact == value,
+        TextFilter::Pattern { pattern } => WildMatch::new(pattern).matches(value),
+        TextFilter::Regex { regex } => {
+            // TODO implement config validation for regex
+            match Regex::new(regex) {
+                Ok(regex) => regex.is_match(value),
+                Err(e) => {
+                    error!("Invalid regex: {}", e);
+                    false
+                }
+            }
+        }
+        TextFilter::Not { not } => !text_matches(value, not),
+    }
+}
+
+/// Returns the matched author filter, or `None` if the rule accepts any author
+fn check_author(
+    author: &Author,
+    rule_authors: Option<&Vec<AuthorFilter>>,
+) -> Result<Option<AuthorFilter>, String> {
+    let rule_authors = match rule_authors {
+        // if None or empty, then it matches any author
+        None => return Ok(None),
+        Some(rule_authors) if rule_authors.is_empty() => return Ok(None),
+        Some(rule_authors) => rule_authors,
+    };
+
+    for author_filter in rule_authors {
+        let result = match author_filter {
+            AuthorFilter::Username { username } => is_author(author, username),
+            AuthorFilter::Email { email } => is_author(author, email),
+            AuthorFilter::Users { users } => users.iter().any(|user| is_author(author, user)),
+            AuthorFilter::GroupFile { members, .. } => {
+                members.iter().any(|member| is_author(author, member))
+            }
+        };
+
+        if result {
+            debug!("Author matches: {:?}", author_filter);
+            return Ok(Some(author_filter.clone()));
+        }
+    }
+
+    Err(format!(
+        "author {} matched no author filter",
+        author.username
+    ))
+}
+
+/// Whether the user is the author's username or email
+fn is_author(author: &Author, user: &str) -> bool {
+    !user.is_empty()
+        && (user.eq_ignore_ascii_case(&author.username) || user.eq_ignore_ascii_case(&author.email))
+}
+
+/// Returns the path filters that matched and the changed files they matched.
+/// If the rule has no path filters, every changed file is considered matched.
+fn check_changed_files(
+    event_paths: &[Path],
+    rule_paths: &Option<Vec<PathFilter>>,
+) -> Result<(Vec<PathFilter>, Vec<Path>), String> {
     let rule_paths = match rule_paths {
         // if None or empty, then it matches any path
-        None => return true,
-        Some(rule_paths) if rule_paths.is_empty() => return true,
+        None => return Ok((vec![], event_paths.to_vec())),
+        Some(rule_paths) if rule_paths.is_empty() => return Ok((vec![], event_paths.to_vec())),
         Some(rule_paths) => rule_paths,
     };
 
-    // check each event_paths
+    let mut matched_filters = vec![false; rule_paths.len()];
+    let mut matched_files = Vec::new();
+
+    // check each event_paths against the filters, first matching filter wins
     for event_path in event_paths {
-        for path_filter in rule_paths {
-            match path_filter {
-                PathFilter::Exact { exact } => {
-                    if exact == event_path {
-                        debug!("Path matches exact: {}", exact);
-                        return true;
-                    }
-                }
-                PathFilter::Pattern { pattern } => {
-                    let pattern = match Pattern::new(pattern) {
-                        Ok(pattern) => pattern,
-                        _ => {
-                            error!("Invalid pattern: {}", pattern);
-                            continue;
-                        }
-                    };
-                    let result = pattern.matches(event_path);
-                    if result {
-                        debug!("Path matches wildcard: {}", pattern);
-                        return true;
-                    }
+        for (index, path_filter) in rule_paths.iter().enumerate() {
+            if check_path(event_path, path_filter) {
+                matched_filters[index] = true;
+                matched_files.push(event_path.clone());
+                break;
+            }
+        }
+    }
+
+    if matched_files.is_empty() {
+        return Err("no changed files matched the path filters".to_string());
+    }
+
+    let matched_filters = rule_paths
+        .iter()
+        .zip(matched_filters)
+        .filter(|(_, matched)| *matched)
+        .map(|(path_filter, _)| path_filter.clone())
+        .collect();
+
+    Ok((matched_filters, matched_files))
+}
+
+/// Returns the names of the event's projects matching the project filters,
+/// or of all projects if the rule has no project filters
+fn check_projects(
+    event_projects: &[Project],
+    rule_projects: &Option<Vec<String>>,
+) -> Result<Vec<String>, String> {
+    let names = event_projects.iter().map(|project| project.name.clone());
+    let rule_projects = match rule_projects {
+        None => return Ok(names.collect()),
+        Some(rule_projects) if rule_projects.is_empty() => return Ok(names.collect()),
+        Some(rule_projects) => rule_projects,
+    };
+
+    let matched: Vec<String> = names
+        .filter(|name| {
+            rule_projects
+                .iter()
+                .any(|pattern| WildMatch::new(pattern).matches(name))
+        })
+        .collect();
+
+    if matched.is_empty() {
+        return Err("no changed project matched the project filters".to_string());
+    }
+    Ok(matched)
+}
+
+fn check_path(event_path: &Path, path_filter: &PathFilter) -> bool {
+    match path_filter {
+        PathFilter::Exact { exact } => {
+            if exact == event_path {
+                debug!("Path matches exact: {}", exact);
+                return true;
+            }
+        }
+        PathFilter::Pattern { pattern } => {
+            let pattern = match Pattern::new(pattern) {
+                Ok(pattern) => pattern,
+                _ => {
+                    error!("Invalid pattern: {}", pattern);
+                    return false;
                 }
-                PathFilter::Regex { regex } => {
-                    // TODO implement config validation for regex
-                    let regex = match Regex::new(regex) {
-                        Ok(regex) => regex,
-                        Err(e) => {
-                            error!("Invalid regex: {}", e);
-                            continue;
-                        }
-                    };
-                    if regex.is_match(event_path) {
-                        debug!("Path matches regex: {}", regex);
-                        return true;
-                    }
+            };
+            if pattern.matches(event_path) {
+                debug!("Path matches wildcard: {}", pattern);
+                return true;
+            }
+        }
+        PathFilter::Regex { regex } => {
+            // TODO implement config validation for regex
+            let regex = match Regex::new(regex) {
+                Ok(regex) => regex,
+                Err(e) => {
+                    error!("Invalid regex: {}", e);
+                    return false;
                 }
+            };
+            if regex.is_match(event_path) {
+                debug!("Path matches regex: {}", regex);
+                return true;
             }
         }
     }
@@ -160,8 +456,11 @@ fn check_changed_files(event_paths: &Vec<Path>, rule_paths: &Option<Vec<PathFilt
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::app::config::rules::PullRequestFilter;
     use crate::app::config::rules::{BranchFilter, PathFilter, Rule};
-    use crate::app::webhooks::types::{Event, EventType};
+    use crate::app::webhooks::types::{Event, EventType, PullRequest};
+    use std::io::Write;
+    use tempfile::NamedTempFile;
 
     #[test]
     fn test_check_branch_exact_match() {
@@ -175,7 +474,7 @@ mod tests {
         let result = check_branch(&event_branch, &rule_branches);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -190,7 +489,7 @@ mod tests {
         let result = check_branch(&event_branch, &rule_branches);
 
         // Verify
-        assert!(!result);
+        assert!(result.is_err());
     }
 
     #[test]
@@ -205,7 +504,7 @@ mod tests {
         let result = check_branch(&event_branch, &rule_branches);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -220,7 +519,7 @@ mod tests {
         let result = check_branch(&event_branch, &rule_branches);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -233,7 +532,7 @@ mod tests {
         let result = check_branch(&event_branch, &rule_branches);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -246,7 +545,7 @@ mod tests {
         let result = check_branch(&event_branch, &rule_branches);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -259,7 +558,7 @@ mod tests {
         let result = check_event_type(&event_type, &rule_event_types);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -272,7 +571,7 @@ mod tests {
         let result = check_event_type(&event_type, &rule_event_types);
 
         // Verify
-        assert!(!result);
+        assert!(result.is_err());
     }
 
     #[test]
@@ -285,7 +584,7 @@ mod tests {
         let result = check_event_type(&event_type, &rule_event_types);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -298,7 +597,7 @@ mod tests {
         let result = check_event_type(&event_type, &rule_event_types);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -313,7 +612,7 @@ mod tests {
         let result = check_changed_files(&event_paths, &rule_paths);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -328,7 +627,7 @@ mod tests {
         let result = check_changed_files(&event_paths, &rule_paths);
 
         // Verify
-        assert!(!result);
+        assert!(result.is_err());
     }
 
     #[test]
@@ -343,7 +642,7 @@ mod tests {
         let result = check_changed_files(&event_paths, &rule_paths);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -358,7 +657,56 @@ mod tests {
         let result = check_changed_files(&event_paths, &rule_paths);
 
         // Verify
-        assert!(result);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_check_changed_files_returns_matched_subset() {
+        // Setup
+        let event_paths = vec![
+            "services/api/main.rs".to_string(),
+            "docs/README.md".to_string(),
+            "services/web/index.ts".to_string(),
+        ];
+        let rule_paths = Some(vec![
+            PathFilter::Pattern {
+                pattern: "services/**".to_string(),
+            },
+            PathFilter::Exact {
+                exact: "Cargo.toml".to_string(),
+            },
+        ]);
+
+        // Execute
+        let (filters, files) = check_changed_files(&event_paths, &rule_paths).unwrap();
+
+        // Verify
+        assert_eq!(
+            filters,
+            vec![PathFilter::Pattern {
+                pattern: "services/**".to_string(),
+            }]
+        );
+        assert_eq!(
+            files,
+            vec![
+                "services/api/main.rs".to_string(),
+                "services/web/index.ts".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_changed_files_no_rules_matches_all_files() {
+        // Setup
+        let event_paths = vec!["src/main.rs".to_string(), "README.md".to_string()];
+
+        // Execute
+        let (filters, files) = check_changed_files(&event_paths, &None).unwrap();
+
+        // Verify
+        assert!(filters.is_empty());
+        assert_eq!(files, event_paths);
     }
 
     #[test]
@@ -368,6 +716,8 @@ mod tests {
             event_type: EventType::Opened,
             branch: "feature/new-feature".to_string(),
             changed_files: vec!["src/main.rs".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
         };
 
         let rule = Rule {
@@ -381,13 +731,45 @@ mod tests {
                 pattern: "src/*.rs".to_string(),
             }]),
             actions: vec![], // Empty for this test
+            ..Default::default()
+        };
+
+        // Execute
+        let result = check(&event, &rule);
+
+        // Verify
+        assert!(result.matched);
+        assert_eq!(result.files, vec!["src/main.rs".to_string()]);
+        assert_eq!(
+            result.branch,
+            Some(BranchFilter::Pattern {
+                pattern: "feature/*".to_string(),
+            })
+        );
+        assert!(result.reason.is_none());
+    }
+
+    #[test]
+    fn test_check_disabled_rule_no_match() {
+        // Setup
+        let event = Event {
+            event_type: EventType::Opened,
+            branch: "main".to_string(),
+            changed_files: vec!["src/main.rs".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
+        };
+        let rule = Rule {
+            enabled: Some(false),
+            ..Default::default()
         };
 
         // Execute
         let result = check(&event, &rule);
 
         // Verify
-        assert!(result);
+        assert!(!result.matched);
+        assert_eq!(result.reason.as_deref(), Some("rule is disabled"));
     }
 
     #[test]
@@ -397,6 +779,8 @@ mod tests {
             event_type: EventType::Modified,
             branch: "feature/new-feature".to_string(),
             changed_files: vec!["src/main.rs".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
         };
 
         let rule = Rule {
@@ -410,13 +794,15 @@ mod tests {
                 pattern: "src/*.rs".to_string(),
             }]),
             actions: vec![], // Empty for this test
+            ..Default::default()
         };
 
         // Execute
         let result = check(&event, &rule);
 
         // Verify
-        assert!(!result);
+        assert!(!result.matched);
+        assert!(result.reason.is_some());
     }
 
     #[test]
@@ -426,6 +812,8 @@ mod tests {
             event_type: EventType::Opened,
             branch: "main".to_string(),
             changed_files: vec!["src/main.rs".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
         };
 
         let rule = Rule {
@@ -439,13 +827,15 @@ mod tests {
                 pattern: "src/*.rs".to_string(),
             }]),
             actions: vec![], // Empty for this test
+            ..Default::default()
         };
 
         // Execute
         let result = check(&event, &rule);
 
         // Verify
-        assert!(!result);
+        assert!(!result.matched);
+        assert!(result.reason.is_some());
     }
 
     #[test]
@@ -455,6 +845,8 @@ mod tests {
             event_type: EventType::Opened,
             branch: "feature/new-feature".to_string(),
             changed_files: vec!["docs/README.md".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
         };
 
         let rule = Rule {
@@ -468,12 +860,237 @@ mod tests {
                 pattern: "src/*.rs".to_string(),
             }]),
             actions: vec![], // Empty for this test
+            ..Default::default()
+        };
+
+        // Execute
+        let result = check(&event, &rule);
+
+        // Verify
+        assert!(!result.matched);
+        assert!(result.reason.is_some());
+    }
+
+    #[test]
+    fn test_check_text_pattern_match() {
+        // Setup
+        let filters = vec![TextFilter::Pattern {
+            pattern: "feat:*".to_string(),
+        }];
+
+        // Execute
+        let result = check_text("title", "feat: add login", Some(&filters));
+
+        // Verify
+        assert_eq!(result.unwrap(), Some(filters[0].clone()));
+    }
+
+    #[test]
+    fn test_check_text_no_match() {
+        // Setup
+        let filters = vec![TextFilter::Exact {
+            exact: "Release".to_string(),
+        }];
+
+        // Execute
+        let result = check_text("title", "feat: add login", Some(&filters));
+
+        // Verify
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_check_text_negated_regex() {
+        // Setup
+        let filters = vec![TextFilter::Not {
+            not: Box::new(TextFilter::Regex {
+                regex: "WIP".to_string(),
+            }),
+        }];
+
+        // Execute
+        let wip = check_text("title", "WIP: add login", Some(&filters));
+        let ready = check_text("title", "add login", Some(&filters));
+
+        // Verify
+        assert!(wip.is_err());
+        assert_eq!(ready.unwrap(), None);
+    }
+
+    #[test]
+    fn test_check_text_negated_and_positive() {
+        // Setup
+        let filters = vec![
+            TextFilter::Regex {
+                regex: "^feat".to_string(),
+            },
+            TextFilter::Not {
+                not: Box::new(TextFilter::Pattern {
+                    pattern: "*WIP*".to_string(),
+                }),
+            },
+        ];
+
+        // Execute
+        let result = check_text("title", "fix: typo", Some(&filters));
+
+        // Verify
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_check_text_no_rules_should_pass() {
+        // Execute
+        let result = check_text("description", "anything", None);
+
+        // Verify
+        assert_eq!(result.unwrap(), None);
+    }
+
+    fn create_test_author() -> Author {
+        Author {
+            username: "renovate".to_string(),
+            email: "Renovate@example.com".to_string(),
+            display_name: "Renovate Bot".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_check_author_username_match() {
+        // Setup
+        let filters = vec![AuthorFilter::Username {
+            username: "renovate".to_string(),
+        }];
+
+        // Execute
+        let result = check_author(&create_test_author(), Some(&filters));
+
+        // Verify
+        assert_eq!(result.unwrap(), Some(filters[0].clone()));
+    }
+
+    #[test]
+    fn test_check_author_email_match_ignores_case() {
+        // Setup
+        let filters = vec![AuthorFilter::Email {
+            email: "renovate@example.com".to_string(),
+        }];
+
+        // Execute
+        let result = check_author(&create_test_author(), Some(&filters));
+
+        // Verify
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_check_author_users_no_match() {
+        // Setup
+        let filters = vec![AuthorFilter::Users {
+            users: vec!["alice".to_string(), "bob@example.com".to_string()],
+        }];
+
+        // Execute
+        let result = check_author(&create_test_author(), Some(&filters));
+
+        // Verify
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_check_author_group_file_match() {
+        // Setup
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"# bots\nalice\n  renovate  \n").unwrap();
+        let mut filter = AuthorFilter::GroupFile {
+            group_file: file.path().display().to_string(),
+            members: vec![],
+        };
+        filter.load().unwrap();
+        let filters = vec![filter];
+
+        // Execute
+        let result = check_author(&create_test_author(), Some(&filters));
+
+        // Verify
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_check_author_group_file_missing() {
+        // Setup
+        let mut filter = AuthorFilter::GroupFile {
+            group_file: "nonexistent-group-file.txt".to_string(),
+            members: vec![],
+        };
+
+        // Execute
+        let loaded = filter.load();
+        let filters = vec![filter];
+        let result = check_author(&create_test_author(), Some(&filters));
+
+        // Verify
+        assert!(loaded.is_err());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_check_complete_rule_no_match_title() {
+        // Setup
+        let event = Event {
+            event_type: EventType::Opened,
+            branch: "feature/new-feature".to_string(),
+            changed_files: vec!["src/main.rs".to_string()],
+            pull_request: PullRequest {
+                title: "WIP: new feature".to_string(),
+                author: create_test_author(),
+                ..Default::default()
+            },
+            projects: vec![],
+        };
+
+        let rule = Rule {
+            pull_request: Some(PullRequestFilter {
+                title: Some(vec![TextFilter::Not {
+                    not: Box::new(TextFilter::Pattern {
+                        pattern: "WIP*".to_string(),
+                    }),
+                }]),
+                authors: Some(vec![AuthorFilter::Username {
+                    username: "renovate".to_string(),
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
         };
 
         // Execute
         let result = check(&event, &rule);
 
         // Verify
-        assert!(!result);
+        assert!(!result.matched);
+        assert!(result.reason.unwrap().contains("title"));
+    }
+
+    #[test]
+    fn test_check_projects() {
+        // Setup
+        let projects: Vec<Project> = ["services/api", "services/web", "docs"]
+            .into_iter()
+            .map(|name| Project {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect();
+
+        // Execute
+        let all = check_projects(&projects, &None);
+        let services = check_projects(&projects, &Some(vec!["services/*".to_string()]));
+        let none = check_projects(&projects, &Some(vec!["tools".to_string()]));
+
+        // Verify
+        assert_eq!(all.unwrap().len(), 3);
+        assert_eq!(services.unwrap(), vec!["services/api", "services/web"]);
+        assert!(none.is_err());
     }
 }
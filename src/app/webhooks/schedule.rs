@@ -0,0 +1,506 @@
+use crate::app::config::calendar::FreezePeriod;
+use crate::app::config::rules::{Schedule, TimeWindow};
+use crate::app::config::FreezeCalendarConfig;
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use tracing::error;
+
+/// How far ahead to look for the schedule to open when queueing blocked actions
+const MAX_QUEUE_DAYS: i64 = 14;
+
+/// Why a schedule blocks actions and when it opens again
+#[derive(Clone, Debug, PartialEq)]
+pub struct Blocked {
+    /// Why the actions are blocked
+    pub reason: String,
+
+    /// When the schedule opens, if it does within the queueing horizon
+    pub opens_at: Option<DateTime<Utc>>,
+}
+
+/// Check whether the schedule allows actions to run at `now`
+pub fn check(
+    schedule: &Schedule,
+    calendars: &[&FreezeCalendarConfig],
+    now: DateTime<Utc>,
+) -> Result<(), Blocked> {
+    // parse the schedule once, invalid entries are rejected when the config is
+    // loaded and are logged and ignored here
+    let compiled = CompiledSchedule::new(schedule, calendars);
+
+    let reason = match compiled.blocked_reason(now) {
+        None => return Ok(()),
+        Some(reason) => reason,
+    };
+
+    Err(Blocked {
+        reason,
+        opens_at: compiled.next_opening(now),
+    })
+}
+
+struct CompiledSchedule {
+    timezone: Tz,
+    allow: Option<Vec<Window>>,
+    block: Vec<Window>,
+    freezes: Vec<CompiledCalendar>,
+}
+
+struct CompiledCalendar {
+    name: String,
+    timezone: Tz,
+    periods: Vec<Period>,
+    windows: Vec<Window>,
+}
+
+struct Window {
+    days: Option<Vec<Weekday>>,
+    start: NaiveTime,
+    end: Option<NaiveTime>,
+}
+
+struct Period {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    reason: Option<String>,
+}
+
+impl CompiledSchedule {
+    fn new(schedule: &Schedule, calendars: &[&FreezeCalendarConfig]) -> Self {
+        let freezes = calendars
+            .iter()
+            .map(|calendar| CompiledCalendar {
+                name: calendar.metadata.name.clone(),
+                timezone: parse_timezone(&calendar.spec.timezone),
+                periods: calendar
+                    .spec
+                    .periods
+                    .iter()
+                    .flatten()
+                    .filter_map(Period::new)
+                    .collect(),
+                windows: compile_windows(&calendar.spec.windows),
+            })
+            .collect();
+
+        Self {
+            timezone: parse_timezone(&schedule.timezone),
+            allow: match &schedule.allow {
+                Some(allow) if !allow.is_empty() => Some(compile_windows(&schedule.allow)),
+                _ => None,
+            },
+            block: compile_windows(&schedule.block),
+            freezes,
+        }
+    }
+
+    fn blocked_reason(&self, at: DateTime<Utc>) -> Option<String> {
+        let local = at.with_timezone(&self.timezone).naive_local();
+
+        if let Some(allow) = &self.allow {
+            if !allow.iter().any(|window| window.contains(&local)) {
+                return Some("outside of the allowed windows".to_string());
+            }
+        }
+
+        if self.block.iter().any(|window| window.contains(&local)) {
+            return Some("inside a blocked window".to_string());
+        }
+
+        for calendar in &self.freezes {
+            let local = at.with_timezone(&calendar.timezone).naive_local();
+
+            for period in &calendar.periods {
+                if period.start <= local && local < period.end {
+                    return Some(format!(
+                        "freeze {}: {}",
+                        calendar.name,
+                        period.reason.as_deref().unwrap_or("freeze period")
+                    ));
+                }
+            }
+
+            if calendar
+                .windows
+                .iter()
+                .any(|window| window.contains(&local))
+            {
+                return Some(format!("freeze {}: recurring freeze window", calendar.name));
+            }
+        }
+
+        None
+    }
+
+    /// Find the first time after `now` when the schedule is open. Whether the
+    /// schedule is open only changes at a window boundary, at midnight or at the
+    /// end of a freeze period, so only those times are checked
+    fn next_opening(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = now + Duration::days(MAX_QUEUE_DAYS);
+
+        let mut boundaries = Vec::new();
+        let windows = self.allow.iter().flatten().chain(&self.block);
+        daily_boundaries(self.timezone, windows, now, limit, &mut boundaries);
+        for calendar in &self.freezes {
+            daily_boundaries(
+                calendar.timezone,
+                &calendar.windows,
+                now,
+                limit,
+                &mut boundaries,
+            );
+            boundaries.extend(
+                calendar
+                    .periods
+                    .iter()
+                    .filter_map(|period| to_utc(calendar.timezone, period.end)),
+            );
+        }
+
+        boundaries.retain(|at| *at > now && *at <= limit);
+        boundaries.sort();
+        boundaries.dedup();
+        boundaries
+            .into_iter()
+            .find(|at| self.blocked_reason(*at).is_none())
+    }
+}
+
+/// Add the midnights and the window starts and ends of every local day between
+/// `from` and `to`
+fn daily_boundaries<'a>(
+    timezone: Tz,
+    windows: impl IntoIterator<Item = &'a Window> + Clone,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    boundaries: &mut Vec<DateTime<Utc>>,
+) {
+    let mut date = from.with_timezone(&timezone).date_naive();
+    let last = to.with_timezone(&timezone).date_naive();
+
+    while date <= last {
+        let times = windows
+            .clone()
+            .into_iter()
+            .flat_map(|window| std::iter::once(window.start).chain(window.end));
+        for time in std::iter::once(NaiveTime::MIN).chain(times) {
+            boundaries.extend(to_utc(timezone, date.and_time(time)));
+        }
+        date = match date.succ_opt() {
+            Some(next) => next,
+            None => return,
+        };
+    }
+}
+
+/// Convert a local time to UTC. A time skipped by a daylight saving change is
+/// moved past the change
+fn to_utc(timezone: Tz, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    timezone
+        .from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            timezone
+                .from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|at| at.with_timezone(&Utc))
+}
+
+impl Window {
+    fn new(window: &TimeWindow) -> Option<Self> {
+        let start = match &window.start {
+            None => NaiveTime::MIN,
+            Some(start) => parse_time(start)?,
+        };
+        let end = match &window.end {
+            None => None,
+            Some(end) => Some(parse_time(end)?),
+        };
+
+        Some(Self {
+            days: window.days.clone(),
+            start,
+            end,
+        })
+    }
+
+    fn contains(&self, local: &NaiveDateTime) -> bool {
+        let time = local.time();
+        let day = local.weekday();
+
+        match self.end {
+            // until the end of the day
+            None => time >= self.start && self.has_day(day),
+            // same day window
+            Some(end) if end > self.start => time >= self.start && time < end && self.has_day(day),
+            // window wraps past midnight, the part after midnight belongs to the previous day
+            Some(end) => {
+                (time >= self.start && self.has_day(day))
+                    || (time < end && self.has_day(day.pred()))
+            }
+        }
+    }
+
+    fn has_day(&self, day: Weekday) -> bool {
+        match &self.days {
+            None => true,
+            Some(days) if days.is_empty() => true,
+            Some(days) => days.contains(&day),
+        }
+    }
+}
+
+impl Period {
+    fn new(period: &FreezePeriod) -> Option<Self> {
+        let start = parse_date_time(&period.start, false)?;
+        let end = parse_date_time(&period.end, true)?;
+
+        Some(Self {
+            start,
+            end,
+            reason: period.reason.clone(),
+        })
+    }
+}
+
+fn compile_windows(windows: &Option<Vec<TimeWindow>>) -> Vec<Window> {
+    windows.iter().flatten().filter_map(Window::new).collect()
+}
+
+fn parse_timezone(timezone: &Option<String>) -> Tz {
+    match timezone {
+        None => Tz::UTC,
+        Some(timezone) => timezone.parse().unwrap_or_else(|e| {
+            error!("Invalid timezone {}, using UTC: {}", timezone, e);
+            Tz::UTC
+        }),
+    }
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    match NaiveTime::parse_from_str(time, "%H:%M") {
+        Ok(time) => Some(time),
+        Err(e) => {
+            error!("Invalid time {}: {}", time, e);
+            None
+        }
+    }
+}
+
+/// Parse "YYYY-MM-DD" or "YYYY-MM-DDTHH:MM". A date is the start of that
+/// day, or the end of it when `end_of_day` is set
+fn parse_date_time(value: &str, end_of_day: bool) -> Option<NaiveDateTime> {
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+        return Some(date_time);
+    }
+
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) if end_of_day => date.succ_opt().map(|d| d.and_time(NaiveTime::MIN)),
+        Ok(date) => Some(date.and_time(NaiveTime::MIN)),
+        Err(e) => {
+            error!("Invalid date {}: {}", value, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::calendar::FreezeCalendarSpec;
+    use crate::app::config::{ConfigKind, Metadata};
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn window(days: Option<Vec<Weekday>>, start: &str, end: Option<&str>) -> TimeWindow {
+        TimeWindow {
+            days,
+            start: Some(start.to_string()),
+            end: end.map(str::to_string),
+        }
+    }
+
+    fn create_test_calendar(periods: Vec<FreezePeriod>) -> FreezeCalendarConfig {
+        FreezeCalendarConfig {
+            api_version: "v1".to_string(),
+            kind: ConfigKind::FreezeCalendar,
+            metadata: Metadata {
+                name: "holidays".to_string(),
+            },
+            spec: FreezeCalendarSpec {
+                timezone: None,
+                periods: Some(periods),
+                windows: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_check_empty_schedule_allows() {
+        // Setup
+        let schedule = Schedule::default();
+
+        // Execute
+        let result = check(&schedule, &[], utc(2025, 10, 17, 18, 0));
+
+        // Verify
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_check_outside_allowed_window_in_timezone() {
+        // Setup: weekdays 09:00-17:00 London time, BST is UTC+1 in October
+        let schedule = Schedule {
+            timezone: Some("Europe/London".to_string()),
+            allow: Some(vec![window(
+                Some(vec![
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ]),
+                "09:00",
+                Some("17:00"),
+            )]),
+            ..Default::default()
+        };
+
+        // Execute: Saturday
+        let result = check(&schedule, &[], utc(2025, 10, 18, 12, 0));
+
+        // Verify: opens Monday 09:00 London
+        let blocked = result.unwrap_err();
+        assert_eq!(blocked.reason, "outside of the allowed windows");
+        assert_eq!(blocked.opens_at, Some(utc(2025, 10, 20, 8, 0)));
+
+        // Friday 15:30 London is allowed
+        assert!(check(&schedule, &[], utc(2025, 10, 17, 14, 30)).is_ok());
+    }
+
+    #[test]
+    fn test_check_blocked_window_until_end_of_day() {
+        // Setup: Friday evening freeze
+        let schedule = Schedule {
+            block: Some(vec![window(Some(vec![Weekday::Fri]), "17:00", None)]),
+            ..Default::default()
+        };
+
+        // Execute
+        let blocked = check(&schedule, &[], utc(2025, 10, 17, 18, 0));
+        let allowed = check(&schedule, &[], utc(2025, 10, 17, 16, 59));
+
+        // Verify
+        let blocked = blocked.unwrap_err();
+        assert_eq!(blocked.reason, "inside a blocked window");
+        assert_eq!(blocked.opens_at, Some(utc(2025, 10, 18, 0, 0)));
+        assert!(allowed.is_ok());
+    }
+
+    #[test]
+    fn test_check_blocked_window_wraps_past_midnight() {
+        // Setup: Friday 22:00 until Saturday 02:00
+        let schedule = Schedule {
+            block: Some(vec![window(
+                Some(vec![Weekday::Fri]),
+                "22:00",
+                Some("02:00"),
+            )]),
+            ..Default::default()
+        };
+
+        // Execute & Verify
+        assert!(check(&schedule, &[], utc(2025, 10, 18, 1, 0)).is_err());
+        assert!(check(&schedule, &[], utc(2025, 10, 18, 2, 0)).is_ok());
+        // Thursday night is not part of the window
+        assert!(check(&schedule, &[], utc(2025, 10, 17, 1, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_check_freeze_calendar_period() {
+        // Setup
+        let calendar = create_test_calendar(vec![FreezePeriod {
+            start: "2025-12-24".to_string(),
+            end: "2025-12-26".to_string(),
+            reason: Some("Christmas".to_string()),
+        }]);
+        let schedule = Schedule {
+            freezes: Some(vec!["holidays".to_string()]),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = check(&schedule, &[&calendar], utc(2025, 12, 26, 12, 0));
+
+        // Verify: the end date is included
+        let blocked = result.unwrap_err();
+        assert_eq!(blocked.reason, "freeze holidays: Christmas");
+        assert_eq!(blocked.opens_at, Some(utc(2025, 12, 27, 0, 0)));
+        assert!(check(&schedule, &[&calendar], utc(2025, 12, 23, 23, 59)).is_ok());
+    }
+
+    #[test]
+    fn test_check_opens_after_freeze_and_blocked_window() {
+        // Setup: the freeze ends on Saturday, blocked until 10:00 on Saturdays
+        let calendar = create_test_calendar(vec![FreezePeriod {
+            start: "2025-12-24".to_string(),
+            end: "2025-12-26".to_string(),
+            reason: None,
+        }]);
+        let schedule = Schedule {
+            block: Some(vec![window(
+                Some(vec![Weekday::Sat]),
+                "00:00",
+                Some("10:00"),
+            )]),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = check(&schedule, &[&calendar], utc(2025, 12, 24, 9, 30));
+
+        // Verify
+        assert_eq!(result.unwrap_err().opens_at, Some(utc(2025, 12, 27, 10, 0)));
+    }
+
+    #[test]
+    fn test_check_freeze_longer_than_queue_horizon() {
+        // Setup
+        let calendar = create_test_calendar(vec![FreezePeriod {
+            start: "2025-12-01T00:00".to_string(),
+            end: "2026-01-31T00:00".to_string(),
+            reason: None,
+        }]);
+        let schedule = Schedule::default();
+
+        // Execute
+        let result = check(&schedule, &[&calendar], utc(2025, 12, 2, 0, 0));
+
+        // Verify
+        let blocked = result.unwrap_err();
+        assert_eq!(blocked.reason, "freeze holidays: freeze period");
+        assert_eq!(blocked.opens_at, None);
+    }
+
+    #[test]
+    fn test_check_invalid_window_is_ignored() {
+        // Setup
+        let schedule = Schedule {
+            timezone: Some("Not/A_Zone".to_string()),
+            block: Some(vec![window(None, "25:99", None)]),
+            ..Default::default()
+        };
+
+        // Execute
+        let result = check(&schedule, &[], utc(2025, 10, 17, 18, 0));
+
+        // Verify
+        assert!(result.is_ok());
+    }
+}
@@ -1,53 +1,114 @@
-use crate::app::config::rules::Action;
 use crate::app::config::Rule;
-use crate::app::webhooks::rule_evaluator;
+use crate::app::webhooks::projects::Project;
+use crate::app::webhooks::rule_evaluator::{self, Evaluation};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use indexmap::IndexMap;
 use serde::Serialize;
-use std::collections::HashMap;
 use strum_macros::{AsRefStr, Display};
-use tracing::debug;
+use tracing::{debug, info};
 
 #[async_trait]
 pub trait WebhookTypeHandler: Send + Sync {
     /// Extract the event from the payload
     async fn extract_event(&self) -> Result<Event>;
 
-    /// Run the webhook handler
-    async fn run(&self) -> Result<Vec<&Action>>;
-
-    // Default value for EventType
-    fn evaluate_rules<'a>(event: &Event, rules: &HashMap<String, &'a Rule>) -> Vec<&'a Action> {
-        let mut actions: Vec<&Action> = Vec::new();
-        // This is where you would handle the webhook payload and apply rules
-        for (rule_name, rule) in rules {
-            // call matches_rule to check if the rule applies
-            let result = rule_evaluator::check(event, rule);
-            if result {
-                debug!("OK Rule {}", rule_name);
-                for action in &rule.actions {
-                    actions.push(action);
+    /// Run the webhook handler, returning the event and the evaluation of every
+    /// subscribed rule
+    async fn run(&self) -> Result<(Event, Vec<EvaluatedRule<'_>>)>;
+
+    /// Evaluate each rule against the event, highest priority first and in
+    /// declaration order otherwise. A matching rule with `stop` set skips the
+    /// rules of lower priority, unless it is a dry run. Rules of the same
+    /// priority are still evaluated.
+    fn evaluate_rules<'a>(
+        event: &Event,
+        rules: &IndexMap<String, &'a Rule>,
+    ) -> Vec<EvaluatedRule<'a>> {
+        // stable sort keeps declaration order for rules with the same priority
+        let mut ordered: Vec<(&String, &&'a Rule)> = rules.iter().collect();
+        ordered.sort_by_key(|(_, rule)| std::cmp::Reverse(rule.priority));
+
+        let mut evaluated: Vec<EvaluatedRule> = Vec::new();
+        let mut stopped_by: Option<(&String, i32)> = None;
+        for (rule_name, rule) in ordered {
+            let evaluation = match stopped_by {
+                Some((stop_rule, stop_priority)) if rule.priority < stop_priority => {
+                    Evaluation::failed(format!("skipped, stopped by rule {}", stop_rule))
+                }
+                _ => rule_evaluator::check(event, rule),
+            };
+
+            if evaluation.matched {
+                debug!("OK Rule {}: {:?}", rule_name, evaluation);
+                if rule.stop && rule.dry_run {
+                    info!("Dry run rule {} would stop remaining rules", rule_name);
+                } else if rule.stop && stopped_by.is_none() {
+                    debug!(
+                        "Rule {} stops evaluation of lower priority rules",
+                        rule_name
+                    );
+                    stopped_by = Some((rule_name, rule.priority));
                 }
             } else {
-                debug!("FAIL Rule {}", rule_name);
+                debug!("FAIL Rule {}: {:?}", rule_name, evaluation);
             }
+
+            evaluated.push(EvaluatedRule {
+                name: rule_name.clone(),
+                rule,
+                evaluation,
+            });
         }
 
-        actions
+        evaluated
     }
 }
 
+/// A rule together with the trace of its evaluation against an event
+#[derive(Clone, Debug)]
+pub struct EvaluatedRule<'a> {
+    pub name: String,
+    pub rule: &'a Rule,
+    pub evaluation: Evaluation,
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct Event {
     #[serde(rename = "type")]
     pub event_type: EventType,
     pub branch: Branch,
     pub changed_files: Vec<Path>,
+    pub pull_request: PullRequest,
+    /// Monorepo projects of the changed files
+    pub projects: Vec<Project>,
 }
 
 pub type Branch = String;
 pub type Path = String;
 
+/// Pull request details of the event
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PullRequest {
+    /// Id of the pull request in its repository
+    pub id: u64,
+    /// Latest commit of the pull request's source branch
+    pub commit: String,
+    /// Link to the pull request in the provider's UI
+    pub link: String,
+    pub title: String,
+    pub description: String,
+    pub author: Author,
+}
+
+/// Author of a pull request
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Author {
+    pub username: String,
+    pub email: String,
+    pub display_name: String,
+}
+
 #[derive(Clone, Debug, PartialEq, AsRefStr, Display, Serialize)]
 pub enum EventType {
     #[strum(serialize = "pr_created")]
@@ -74,3 +135,190 @@ impl TryFrom<&str> for EventType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::rules::BranchFilter;
+
+    struct TestHandler;
+
+    #[async_trait]
+    impl WebhookTypeHandler for TestHandler {
+        async fn extract_event(&self) -> Result<Event> {
+            Err(anyhow!("test handler has no payload"))
+        }
+
+        async fn run(&self) -> Result<(Event, Vec<EvaluatedRule<'_>>)> {
+            Err(anyhow!("test handler has no payload"))
+        }
+    }
+
+    fn create_test_event(branch: &str) -> Event {
+        Event {
+            event_type: EventType::Opened,
+            branch: branch.to_string(),
+            changed_files: vec!["src/main.rs".to_string()],
+            pull_request: PullRequest::default(),
+            projects: vec![],
+        }
+    }
+
+    #[test]
+    fn test_evaluate_rules_declaration_order() {
+        // Setup
+        let event = create_test_event("main");
+        let rule = Rule::default();
+        let mut rules = IndexMap::new();
+        rules.insert("c".to_string(), &rule);
+        rules.insert("a".to_string(), &rule);
+        rules.insert("b".to_string(), &rule);
+
+        // Execute
+        let evaluated = TestHandler::evaluate_rules(&event, &rules);
+
+        // Verify
+        let names: Vec<&str> = evaluated.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_evaluate_rules_priority_order() {
+        // Setup
+        let event = create_test_event("main");
+        let low = Rule {
+            priority: -1,
+            ..Default::default()
+        };
+        let default = Rule::default();
+        let high = Rule {
+            priority: 10,
+            ..Default::default()
+        };
+        let mut rules = IndexMap::new();
+        rules.insert("low".to_string(), &low);
+        rules.insert("default-1".to_string(), &default);
+        rules.insert("high".to_string(), &high);
+        rules.insert("default-2".to_string(), &default);
+
+        // Execute
+        let evaluated = TestHandler::evaluate_rules(&event, &rules);
+
+        // Verify
+        let names: Vec<&str> = evaluated.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["high", "default-1", "default-2", "low"]);
+    }
+
+    #[test]
+    fn test_evaluate_rules_stop_skips_remaining_rules() {
+        // Setup
+        let event = create_test_event("hotfix/urgent");
+        let hotfix = Rule {
+            branches: Some(vec![BranchFilter::Pattern {
+                pattern: "hotfix/*".to_string(),
+            }]),
+            priority: 10,
+            stop: true,
+            ..Default::default()
+        };
+        let ci = Rule::default();
+        let mut rules = IndexMap::new();
+        rules.insert("ci".to_string(), &ci);
+        rules.insert("hotfix".to_string(), &hotfix);
+
+        // Execute
+        let evaluated = TestHandler::evaluate_rules(&event, &rules);
+
+        // Verify
+        assert_eq!(evaluated[0].name, "hotfix");
+        assert!(evaluated[0].evaluation.matched);
+        assert_eq!(evaluated[1].name, "ci");
+        assert!(!evaluated[1].evaluation.matched);
+        assert_eq!(
+            evaluated[1].evaluation.reason.as_deref(),
+            Some("skipped, stopped by rule hotfix")
+        );
+    }
+
+    #[test]
+    fn test_evaluate_rules_stop_keeps_same_priority_rules() {
+        // Setup
+        let event = create_test_event("main");
+        let deploy = Rule {
+            priority: 10,
+            stop: true,
+            ..Default::default()
+        };
+        let notify = Rule {
+            priority: 10,
+            ..Default::default()
+        };
+        let ci = Rule::default();
+        let mut rules = IndexMap::new();
+        rules.insert("deploy".to_string(), &deploy);
+        rules.insert("notify".to_string(), &notify);
+        rules.insert("ci".to_string(), &ci);
+
+        // Execute
+        let evaluated = TestHandler::evaluate_rules(&event, &rules);
+
+        // Verify
+        assert!(evaluated[0].evaluation.matched);
+        assert_eq!(evaluated[1].name, "notify");
+        assert!(evaluated[1].evaluation.matched);
+        assert_eq!(evaluated[2].name, "ci");
+        assert_eq!(
+            evaluated[2].evaluation.reason.as_deref(),
+            Some("skipped, stopped by rule deploy")
+        );
+    }
+
+    #[test]
+    fn test_evaluate_rules_dry_run_does_not_stop() {
+        // Setup
+        let event = create_test_event("main");
+        let dry_run = Rule {
+            priority: 10,
+            stop: true,
+            dry_run: true,
+            ..Default::default()
+        };
+        let ci = Rule::default();
+        let mut rules = IndexMap::new();
+        rules.insert("ci".to_string(), &ci);
+        rules.insert("dry-run".to_string(), &dry_run);
+
+        // Execute
+        let evaluated = TestHandler::evaluate_rules(&event, &rules);
+
+        // Verify
+        assert_eq!(evaluated[0].name, "dry-run");
+        assert!(evaluated[0].evaluation.matched);
+        assert!(evaluated[1].evaluation.matched);
+    }
+
+    #[test]
+    fn test_evaluate_rules_stop_only_when_matched() {
+        // Setup
+        let event = create_test_event("feature/new");
+        let hotfix = Rule {
+            branches: Some(vec![BranchFilter::Pattern {
+                pattern: "hotfix/*".to_string(),
+            }]),
+            priority: 10,
+            stop: true,
+            ..Default::default()
+        };
+        let ci = Rule::default();
+        let mut rules = IndexMap::new();
+        rules.insert("hotfix".to_string(), &hotfix);
+        rules.insert("ci".to_string(), &ci);
+
+        // Execute
+        let evaluated = TestHandler::evaluate_rules(&event, &rules);
+
+        // Verify
+        assert!(!evaluated[0].evaluation.matched);
+        assert!(evaluated[1].evaluation.matched);
+    }
+}
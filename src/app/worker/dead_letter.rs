@@ -0,0 +1,246 @@
+use super::Job;
+use crate::app::{actions::FailedAction, Error};
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Rule run whose action failed after its retries were exhausted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Id of the failed job
+    pub id: String,
+    pub run_id: String,
+    pub rule_name: String,
+    pub failed_at: DateTime<Utc>,
+    pub error: String,
+    /// Position of the failed action in the rule
+    pub action: usize,
+    /// Failed action with its templates rendered, environment variables excluded
+    pub request: Value,
+    /// Event of the webhook delivery
+    pub event: Value,
+    /// Job replaying the rule's actions, without its chat webhook URLs
+    pub job: Job,
+}
+
+impl DeadLetter {
+    pub fn new(job: &Job, failed: &FailedAction) -> Self {
+        Self {
+            id: job.id.clone(),
+            run_id: job.run_id.clone(),
+            rule_name: job.rule_name.clone(),
+            failed_at: Utc::now(),
+            error: failed.error.to_string(),
+            action: failed.index,
+            request: failed.request.clone(),
+            event: job.context.get("event").cloned().unwrap_or_default(),
+            job: job.redacted(),
+        }
+    }
+}
+
+/// Directory holding one JSON file per dead letter
+#[derive(Debug)]
+pub struct DeadLetterStore {
+    dir: PathBuf,
+}
+
+impl DeadLetterStore {
+    /// Open the dead letter directory, creating it if needed
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| {
+            format!("Failed to create dead letter directory: {}", dir.display())
+        })?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// Store a dead letter
+    pub fn add(&self, dead_letter: &DeadLetter) -> Result<(), Error> {
+        let path = self.path(&dead_letter.id)?;
+        let content = serde_json::to_string_pretty(dead_letter)
+            .map_err(|e| Error::Handler(format!("Failed to serialize dead letter: {}", e)))?;
+
+        // write then rename, so that a dead letter is never read half written
+        let partial = path.with_extension("partial");
+        fs::write(&partial, content)
+            .and_then(|_| fs::rename(&partial, &path))
+            .map_err(|e| {
+                Error::Handler(format!(
+                    "Failed to write dead letter {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
+    }
+
+    /// List the dead letters, oldest first
+    pub fn list(&self) -> Result<Vec<DeadLetter>, Error> {
+        let entries = fs::read_dir(&self.dir).map_err(|e| {
+            Error::Handler(format!(
+                "Failed to read dead letter directory {}: {}",
+                self.dir.display(),
+                e
+            ))
+        })?;
+
+        let mut dead_letters = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                dead_letters.push(Self::read(&path)?);
+            }
+        }
+        dead_letters.sort_by_key(|dead_letter| dead_letter.failed_at);
+
+        Ok(dead_letters)
+    }
+
+    /// Get a dead letter by id
+    pub fn get(&self, id: &str) -> Result<DeadLetter, Error> {
+        let path = self.path(id)?;
+        if !path.exists() {
+            return Err(Error::DeadLetterNotFound(id.to_string()));
+        }
+
+        Self::read(&path)
+    }
+
+    /// Remove a dead letter by id
+    pub fn remove(&self, id: &str) -> Result<(), Error> {
+        let path = self.path(id)?;
+        if !path.exists() {
+            return Err(Error::DeadLetterNotFound(id.to_string()));
+        }
+
+        fs::remove_file(&path).map_err(|e| {
+            Error::Handler(format!(
+                "Failed to remove dead letter {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    fn read(path: &Path) -> Result<DeadLetter, Error> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            Error::Handler(format!(
+                "Failed to read dead letter {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        serde_json::from_str(&content).map_err(|e| {
+            Error::Handler(format!(
+                "Failed to parse dead letter {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Path of a dead letter. Ids are generated, anything but letters, digits
+    /// and dashes is rejected so that an id cannot point outside the directory
+    fn path(&self, id: &str) -> Result<PathBuf, Error> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(Error::DeadLetterNotFound(id.to_string()));
+        }
+
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::{rules::SlackAction, Action, Rule};
+    use serde_json::json;
+    use tempfile::tempdir;
+    use tera::Context;
+
+    fn create_test_dead_letter(id: &str) -> DeadLetter {
+        let mut context = Context::new();
+        context.insert("event", &json!({"branch": "main"}));
+        let job = Job {
+            id: id.to_string(),
+            run_id: "run-1".to_string(),
+            rule_name: "rule".to_string(),
+            rule: Rule {
+                actions: vec![Action {
+                    slack: Some(SlackAction {
+                        webhook_url: "https://hooks.slack.com/services/T0/B0/secret".to_string(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            context,
+            not_before: None,
+        };
+        let failed = FailedAction {
+            index: 1,
+            request: json!({"http": {"url": "http://localhost/main"}}),
+            error: Error::Action("failed".to_string()),
+            results: Vec::new(),
+        };
+
+        DeadLetter::new(&job, &failed)
+    }
+
+    #[test]
+    fn test_dead_letter_store() {
+        // Setup
+        let dir = tempdir().unwrap();
+        let store = DeadLetterStore::open(&dir.path().join("dead-letters")).unwrap();
+
+        // Execute
+        store.add(&create_test_dead_letter("a")).unwrap();
+        store.add(&create_test_dead_letter("b")).unwrap();
+
+        // Verify
+        let ids: Vec<_> = store
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|dead_letter| dead_letter.id)
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+
+        let dead_letter = store.get("a").unwrap();
+        assert_eq!(dead_letter.action, 1);
+        assert_eq!(dead_letter.event, json!({"branch": "main"}));
+        assert_eq!(dead_letter.request["http"]["url"], "http://localhost/main");
+        assert!(dead_letter.error.contains("failed"));
+        let stored = fs::read_to_string(dir.path().join("dead-letters/a.json")).unwrap();
+        assert!(!stored.contains("secret"));
+
+        store.remove("a").unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+        assert!(matches!(store.get("a"), Err(Error::DeadLetterNotFound(_))));
+        assert!(matches!(
+            store.remove("a"),
+            Err(Error::DeadLetterNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_dead_letter_store_rejects_path_ids() {
+        let dir = tempdir().unwrap();
+        let store = DeadLetterStore::open(dir.path()).unwrap();
+
+        assert!(matches!(
+            store.get("../server"),
+            Err(Error::DeadLetterNotFound(_))
+        ));
+    }
+}
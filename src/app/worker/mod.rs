@@ -0,0 +1,659 @@
+pub mod dead_letter;
+pub mod store;
+
+use crate::app::{
+    actions::{exec_rule_actions, ActionResult, FailedAction},
+    config::{rules::Rule, server::ExecutionSpec, Config},
+    Error,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use dead_letter::{DeadLetter, DeadLetterStore};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use store::JobStore;
+use tera::Context;
+use tokio::sync::{mpsc, Semaphore};
+use tracing::{debug, error, info, instrument, warn};
+
+/// Actions of a matched rule waiting for a worker
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    /// Unique id of the job
+    pub id: String,
+    /// Id of the webhook delivery the job belongs to
+    pub run_id: String,
+    pub rule_name: String,
+    pub rule: Rule,
+    /// Template context of the rule's actions
+    #[serde(with = "context_value")]
+    pub context: Context,
+    /// The job does not run before this time, e.g. until its rule's schedule opens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<DateTime<Utc>>,
+}
+
+/// Placeholder of the chat webhook URLs of persisted jobs
+const REDACTED_URL: &str = "[redacted]";
+
+impl Job {
+    /// Copy of the job to persist, in the queue file or as a dead letter. Literal
+    /// Slack and Teams webhook URLs are secrets and are redacted; templated ones,
+    /// e.g. read from the environment, are rendered when the action runs and kept
+    pub fn redacted(&self) -> Job {
+        let mut job = self.clone();
+        for url in webhook_urls(&mut job.rule) {
+            if !url.contains("{{") && !url.contains("{%") {
+                *url = REDACTED_URL.to_string();
+            }
+        }
+        job
+    }
+
+    /// Restore the webhook URLs of a persisted job from the rule configured under
+    /// its name. A URL is left redacted, and its action fails, when the rule is no
+    /// longer configured or its chat actions changed
+    pub fn restore(&mut self, config: &Config) {
+        let mut configured = config.find_rule(&self.rule_name).cloned();
+        let configured_urls = configured.as_mut().map(webhook_urls).unwrap_or_default();
+        let urls = webhook_urls(&mut self.rule);
+        let restorable = urls.len() == configured_urls.len();
+        for (index, url) in urls.into_iter().enumerate() {
+            if url != REDACTED_URL {
+                continue;
+            }
+            match configured_urls.get(index).filter(|_| restorable) {
+                Some(configured_url) => *url = configured_url.to_string(),
+                None => warn!(
+                    "Run {} rule {}: chat webhook URL cannot be restored, the rule changed",
+                    self.run_id, self.rule_name
+                ),
+            }
+        }
+    }
+}
+
+/// Webhook URLs of the Slack and Teams actions of a rule, in declaration order
+fn webhook_urls(rule: &mut Rule) -> Vec<&mut String> {
+    [
+        &mut rule.actions,
+        &mut rule.on_success,
+        &mut rule.on_failure,
+        &mut rule.always,
+    ]
+    .into_iter()
+    .flatten()
+    .flat_map(|action| {
+        let slack = action.slack.as_mut().map(|slack| &mut slack.webhook_url);
+        let teams = action.teams.as_mut().map(|teams| &mut teams.webhook_url);
+        slack.into_iter().chain(teams)
+    })
+    .collect()
+}
+
+/// Times a job may be started without finishing, e.g. because the server crashed
+/// while it ran, before it is given up on when the queue file is recovered
+const MAX_JOB_STARTS: usize = 3;
+
+/// Background workers running the actions of matched rules with bounded concurrency.
+/// A job with a `not_before` time waits outside of the queue until it is due.
+/// With a queue file, queued jobs are persisted and jobs pending when the server
+/// stopped run again on start; otherwise they are held in memory only.
+///
+/// Delivery is at least once: a job is recorded as done only after its actions ran
+/// and, when an action failed, after its dead letter was stored. A job interrupted
+/// in between runs again from its first action, so actions should be idempotent.
+/// A job interrupted `MAX_JOB_STARTS` times is stored as a dead letter instead
+#[derive(Clone, Debug)]
+pub struct WorkerPool {
+    sender: mpsc::Sender<Job>,
+    store: Option<Arc<JobStore>>,
+}
+
+impl WorkerPool {
+    /// Start the workers. Must be called within a tokio runtime
+    pub fn start(
+        spec: &ExecutionSpec,
+        config: &Config,
+        dead_letters: Option<Arc<DeadLetterStore>>,
+    ) -> Result<Self> {
+        let (store, mut pending) = match &spec.queue_file {
+            Some(path) => {
+                let (store, pending) = JobStore::open(path)?;
+                (Some(Arc::new(store)), pending)
+            }
+            None => (None, Vec::new()),
+        };
+        for (job, _) in &mut pending {
+            job.restore(config);
+        }
+
+        let (sender, mut receiver) = mpsc::channel::<Job>(spec.queue_size.max(1));
+        let permits = Arc::new(Semaphore::new(spec.workers.max(1)));
+        info!(
+            "Starting {} action workers with a queue of {}",
+            spec.workers, spec.queue_size
+        );
+
+        let worker_store = store.clone();
+        let worker_dead_letters = dead_letters.clone();
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                let store = worker_store.clone();
+                let dead_letters = worker_dead_letters.clone();
+
+                // a delayed job leaves the queue and waits for a free worker once due
+                if let Some(delay) = job
+                    .not_before
+                    .and_then(|not_before| (not_before - Utc::now()).to_std().ok())
+                {
+                    info!(
+                        "Run {} rule {} delayed until {}",
+                        job.run_id,
+                        job.rule_name,
+                        job.not_before.unwrap_or_default()
+                    );
+                    let permits = permits.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        let Ok(_permit) = permits.acquire_owned().await else {
+                            return;
+                        };
+                        run_job(job, store.as_deref(), dead_letters.as_deref()).await;
+                    });
+                    continue;
+                }
+
+                // wait for a free worker before taking the next job
+                let Ok(permit) = permits.clone().acquire_owned().await else {
+                    return;
+                };
+                tokio::spawn(async move {
+                    run_job(job, store.as_deref(), dead_letters.as_deref()).await;
+                    drop(permit);
+                });
+            }
+        });
+
+        // recovered jobs are already in the queue file, wait for room in the queue
+        let recovery_sender = sender.clone();
+        let recovery_store = store.clone();
+        tokio::spawn(async move {
+            let pending =
+                recover_jobs(pending, recovery_store.as_deref(), dead_letters.as_deref()).await;
+            for job in pending {
+                info!("Resuming run {} rule {}", job.run_id, job.rule_name);
+                if recovery_sender.send(job).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self { sender, store })
+    }
+
+    /// Queue the jobs of a webhook delivery. Either all jobs are queued or, when
+    /// the queue does not have room for all of them, none: the queue slots are
+    /// reserved for all jobs before any job is sent
+    pub async fn submit(&self, jobs: Vec<Job>) -> Result<(), Error> {
+        let permits = self
+            .sender
+            .try_reserve_many(jobs.len())
+            .map_err(|e| match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    Error::QueueFull(format!("{} rule runs do not fit in the queue", jobs.len()))
+                }
+                mpsc::error::TrySendError::Closed(_) => {
+                    Error::Handler("action workers stopped".to_string())
+                }
+            })?;
+
+        // persist all jobs before any is sent, so that a failed write queues nothing
+        if let Some(store) = &self.store {
+            if let Err(e) = store.enqueued(&jobs).await {
+                // some of the jobs may have been written
+                for job in &jobs {
+                    if let Err(e) = store.done(&job.id).await {
+                        error!("Failed to cancel job {}: {:?}", job.id, e);
+                    }
+                }
+                return Err(e);
+            }
+        }
+
+        for (permit, job) in permits.zip(jobs) {
+            debug!("Queueing run {} rule {}", job.run_id, job.rule_name);
+            permit.send(job);
+        }
+
+        Ok(())
+    }
+}
+
+/// Drop the recovered jobs that were started `MAX_JOB_STARTS` times, storing them
+/// as dead letters, and return the jobs to run again
+async fn recover_jobs(
+    pending: Vec<(Job, usize)>,
+    store: Option<&JobStore>,
+    dead_letters: Option<&DeadLetterStore>,
+) -> Vec<Job> {
+    let mut jobs = Vec::new();
+    for (job, starts) in pending {
+        if starts < MAX_JOB_STARTS {
+            jobs.push(job);
+            continue;
+        }
+
+        error!(
+            "Run {} rule {} was interrupted {} times, giving up",
+            job.run_id, job.rule_name, starts
+        );
+        let failed = FailedAction {
+            index: 0,
+            request: serde_json::Value::Null,
+            error: Error::Handler(format!("job was interrupted {} times", starts)),
+            results: Vec::new(),
+        };
+        if store_dead_letter(&job, &failed, dead_letters) {
+            if let Some(store) = store {
+                if let Err(e) = store.done(&job.id).await {
+                    error!("Failed to record job {} as done: {:?}", job.id, e);
+                }
+            }
+        }
+    }
+    jobs
+}
+
+/// Run the actions of a job and return their results. When an action fails the
+/// job is stored as a dead letter
+#[instrument(skip_all, fields(run_id = %job.run_id, rule = %job.rule_name))]
+pub async fn exec_job(
+    job: &Job,
+    dead_letters: Option<&DeadLetterStore>,
+) -> Result<Vec<ActionResult>, FailedAction> {
+    debug!("Run {} executing rule {}", job.run_id, job.rule_name);
+
+    let failed = match exec_rule_actions(&job.rule_name, &job.rule, job.context.clone()).await {
+        Ok(results) => return Ok(results),
+        Err(failed) => failed,
+    };
+
+    store_dead_letter(job, &failed, dead_letters);
+    Err(failed)
+}
+
+/// Store a failed job as a dead letter. Returns false when the dead letter could not
+/// be written, true when it was written or no dead letters are kept
+fn store_dead_letter(
+    job: &Job,
+    failed: &FailedAction,
+    dead_letters: Option<&DeadLetterStore>,
+) -> bool {
+    let Some(dead_letters) = dead_letters else {
+        return true;
+    };
+
+    match dead_letters.add(&DeadLetter::new(job, failed)) {
+        Ok(()) => {
+            info!(
+                "Run {} rule {} stored as dead letter {}",
+                job.run_id, job.rule_name, job.id
+            );
+            true
+        }
+        Err(e) => {
+            error!("Failed to store dead letter {}: {:?}", job.id, e);
+            false
+        }
+    }
+}
+
+/// Run a queued job, then remove it from the queue file. A failed job whose dead
+/// letter could not be stored is left in the queue file and runs again on start
+#[instrument(skip_all, fields(run_id = %job.run_id, rule = %job.rule_name))]
+async fn run_job(job: Job, store: Option<&JobStore>, dead_letters: Option<&DeadLetterStore>) {
+    if let Some(store) = store {
+        if let Err(e) = store.started(&job.id).await {
+            error!("Failed to record job {} as started: {:?}", job.id, e);
+        }
+    }
+
+    let finished = match exec_rule_actions(&job.rule_name, &job.rule, job.context.clone()).await {
+        Ok(_) => {
+            info!("Run {} rule {} succeeded", job.run_id, job.rule_name);
+            true
+        }
+        Err(failed) => {
+            error!(
+                "Run {} rule {} failed: {}",
+                job.run_id, job.rule_name, failed.error
+            );
+            store_dead_letter(&job, &failed, dead_letters)
+        }
+    };
+
+    if let (true, Some(store)) = (finished, store) {
+        if let Err(e) = store.done(&job.id).await {
+            error!("Failed to record job {} as done: {:?}", job.id, e);
+        }
+    }
+}
+
+/// (De)serialize a template context as its JSON value. The environment variables
+/// are left out, so that secrets are not written to the queue file, and are read
+/// again when the context is restored
+mod context_value {
+    use crate::app::template;
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value;
+    use tera::Context;
+
+    pub fn serialize<S: Serializer>(context: &Context, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = context.clone().into_json();
+        if let Some(object) = value.as_object_mut() {
+            object.remove("env");
+        }
+        value.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Context, D::Error> {
+        let mut context =
+            Context::from_value(Value::deserialize(deserializer)?).map_err(D::Error::custom)?;
+        template::insert_env_context(&mut context);
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::{rules::HttpAction, Action, ConfigType, RulesConfig};
+    use std::time::Duration;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn create_test_job(url: String) -> Job {
+        Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            run_id: "run-1".to_string(),
+            rule_name: "rule".to_string(),
+            rule: Rule {
+                actions: vec![Action {
+                    http: Some(HttpAction {
+                        method: "POST".to_string(),
+                        url,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            context: Context::new(),
+            not_before: None,
+        }
+    }
+
+    #[test]
+    fn test_job_redacts_and_restores_webhook_urls() {
+        // Setup
+        let rules: RulesConfig = serde_yaml::from_str(
+            r#"
+apiVersion: v1
+kind: Rules
+metadata:
+  name: chat
+spec:
+  rules:
+    notify:
+      webhooks: ["push"]
+      actions:
+        - slack:
+            webhook_url: "https://hooks.slack.com/services/T0/B0/secret"
+        - slack:
+            webhook_url: "{{ env.SLACK_WEBHOOK_URL }}"
+"#,
+        )
+        .unwrap();
+        let config = Config {
+            configs: vec![ConfigType::Rules(rules.clone())],
+        };
+        let job = Job {
+            rule_name: "chat-notify".to_string(),
+            rule: rules.spec.rules["notify"].clone(),
+            ..create_test_job("http://localhost".to_string())
+        };
+
+        // Execute
+        let redacted = job.redacted();
+        let mut restored = redacted.clone();
+        restored.restore(&config);
+        let mut unknown = Job {
+            rule_name: "chat-removed".to_string(),
+            ..redacted.clone()
+        };
+        unknown.restore(&config);
+
+        // Verify
+        let persisted = serde_json::to_string(&redacted).unwrap();
+        assert!(!persisted.contains("secret"));
+        assert!(persisted.contains("{{ env.SLACK_WEBHOOK_URL }}"));
+        assert_eq!(
+            serde_json::to_value(&restored.rule).unwrap(),
+            serde_json::to_value(&job.rule).unwrap()
+        );
+        assert_eq!(
+            unknown.rule.actions[0].slack.as_ref().unwrap().webhook_url,
+            REDACTED_URL
+        );
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_runs_jobs() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        let pool = WorkerPool::start(&ExecutionSpec::default(), &Config::new(), None).unwrap();
+
+        // Execute
+        let result = pool
+            .submit(vec![
+                create_test_job(mock_server.uri()),
+                create_test_job(mock_server.uri()),
+            ])
+            .await;
+
+        // Verify
+        assert!(result.is_ok());
+        for _ in 0..50 {
+            if mock_server.received_requests().await.unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(mock_server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_delays_jobs() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let pool = WorkerPool::start(&ExecutionSpec::default(), &Config::new(), None).unwrap();
+        let mut job = create_test_job(mock_server.uri());
+        job.not_before = Some(Utc::now() + chrono::Duration::milliseconds(300));
+
+        // Execute
+        let result = pool.submit(vec![job]).await;
+
+        // Verify
+        assert!(result.is_ok());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(mock_server.received_requests().await.unwrap().is_empty());
+        for _ in 0..50 {
+            if mock_server.received_requests().await.unwrap().len() == 1 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("delayed job did not run");
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_rejects_jobs_when_full() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+            .mount(&mock_server)
+            .await;
+        let pool = WorkerPool::start(
+            &ExecutionSpec {
+                workers: 1,
+                queue_size: 1,
+                ..Default::default()
+            },
+            &Config::new(),
+            None,
+        )
+        .unwrap();
+
+        // Execute
+        let first = pool.submit(vec![create_test_job(mock_server.uri())]).await;
+        let too_many = pool
+            .submit(vec![
+                create_test_job(mock_server.uri()),
+                create_test_job(mock_server.uri()),
+            ])
+            .await;
+
+        // Verify
+        assert!(first.is_ok());
+        assert!(matches!(too_many, Err(Error::QueueFull(_))));
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_resumes_pending_jobs() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let queue_file = dir.path().join("jobs.jsonl");
+        {
+            let (store, _) = JobStore::open(&queue_file).unwrap();
+            store
+                .enqueued(&[create_test_job(mock_server.uri())])
+                .await
+                .unwrap();
+        }
+
+        // Execute
+        let _pool = WorkerPool::start(
+            &ExecutionSpec {
+                queue_file: Some(queue_file.clone()),
+                ..Default::default()
+            },
+            &Config::new(),
+            None,
+        )
+        .unwrap();
+
+        // Verify
+        for _ in 0..50 {
+            let queue = std::fs::read_to_string(&queue_file).unwrap();
+            if mock_server.received_requests().await.unwrap().len() == 1
+                && queue.contains(r#""op":"done""#)
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("pending job was not resumed");
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_gives_up_interrupted_jobs() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let queue_file = dir.path().join("jobs.jsonl");
+        let dead_letters = Arc::new(DeadLetterStore::open(&dir.path().join("dead")).unwrap());
+        let job = create_test_job(mock_server.uri());
+        {
+            let (store, _) = JobStore::open(&queue_file).unwrap();
+            store.enqueued(std::slice::from_ref(&job)).await.unwrap();
+            for _ in 0..MAX_JOB_STARTS {
+                store.started(&job.id).await.unwrap();
+            }
+        }
+
+        // Execute
+        let _pool = WorkerPool::start(
+            &ExecutionSpec {
+                queue_file: Some(queue_file.clone()),
+                ..Default::default()
+            },
+            &Config::new(),
+            Some(dead_letters.clone()),
+        )
+        .unwrap();
+
+        // Verify: recovery runs in the background
+        for _ in 0..50 {
+            let queue = std::fs::read_to_string(&queue_file).unwrap();
+            if queue.contains(r#""op":"done""#) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let dead_letter = dead_letters.get(&job.id).unwrap();
+        assert!(
+            dead_letter.error.contains("interrupted"),
+            "{}",
+            dead_letter.error
+        );
+        let queue = std::fs::read_to_string(&queue_file).unwrap();
+        assert!(queue.contains(r#""op":"done""#));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    #[tokio::test]
+    async fn test_exec_job_stores_dead_letter() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letters = DeadLetterStore::open(dir.path()).unwrap();
+        let job = create_test_job(mock_server.uri());
+
+        // Execute
+        let result = exec_job(&job, Some(&dead_letters)).await;
+
+        // Verify
+        assert!(result.is_err());
+        let dead_letter = dead_letters.get(&job.id).unwrap();
+        assert_eq!(dead_letter.rule_name, "rule");
+        assert_eq!(dead_letter.action, 0);
+        assert!(dead_letter.error.contains("500"), "{}", dead_letter.error);
+    }
+}
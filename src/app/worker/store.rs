@@ -0,0 +1,280 @@
+use super::Job;
+use crate::app::Error;
+use anyhow::{Context as _, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+/// Entry of the queue file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Record {
+    /// A job was queued
+    Enqueue { job: Box<Job> },
+    /// A worker started running a job
+    Start { id: String },
+    /// A job finished and is no longer pending
+    Done { id: String },
+}
+
+/// Append-only file holding the queued jobs, so that pending jobs survive a restart.
+/// Each line is a JSON record; the file is compacted to the pending jobs when opened
+///
+/// Jobs are delivered at least once: a job interrupted after some of its actions ran
+/// but before it was recorded as done runs again, from its first action, on the next
+/// start. The starts of each job are recorded, so that a job interrupted repeatedly
+/// can be given up on instead of running forever
+///
+/// Records are written and synced on the blocking thread pool, so that the disk
+/// does not hold up the runtime threads
+#[derive(Debug)]
+pub struct JobStore {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl JobStore {
+    /// Open the queue file, creating it if needed, and return the pending jobs in queue
+    /// order with the number of times each was started
+    pub fn open(path: &Path) -> Result<(Self, Vec<(Job, usize)>)> {
+        let pending = Self::read_pending(path)?;
+
+        // compact the file to the pending jobs
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create queue directory: {}", parent.display())
+            })?;
+        }
+        let compacted = path.with_extension("compact");
+        {
+            let mut file = File::create(&compacted)
+                .with_context(|| format!("Failed to create queue file: {}", compacted.display()))?;
+            for (job, starts) in pending.values() {
+                writeln!(
+                    file,
+                    "{}",
+                    serde_json::to_string(&Record::Enqueue {
+                        job: Box::new(job.clone())
+                    })?
+                )?;
+                for _ in 0..*starts {
+                    writeln!(
+                        file,
+                        "{}",
+                        serde_json::to_string(&Record::Start { id: job.id.clone() })?
+                    )?;
+                }
+            }
+            file.sync_all()?;
+        }
+        fs::rename(&compacted, path)
+            .with_context(|| format!("Failed to replace queue file: {}", path.display()))?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open queue file: {}", path.display()))?;
+        if !pending.is_empty() {
+            info!(
+                "Recovered {} pending jobs from {}",
+                pending.len(),
+                path.display()
+            );
+        }
+
+        let store = Self {
+            path: path.to_path_buf(),
+            file: Arc::new(Mutex::new(file)),
+        };
+        Ok((store, pending.into_values().collect()))
+    }
+
+    /// Record queued jobs, without their chat webhook URLs, with a single sync
+    pub async fn enqueued(&self, jobs: &[Job]) -> Result<(), Error> {
+        let records: Vec<Record> = jobs
+            .iter()
+            .map(|job| Record::Enqueue {
+                job: Box::new(job.redacted()),
+            })
+            .collect();
+        self.append(&records).await
+    }
+
+    /// Record that a worker started running a job
+    pub async fn started(&self, id: &str) -> Result<(), Error> {
+        self.append(&[Record::Start { id: id.to_string() }]).await
+    }
+
+    /// Record a finished job, or a queued job that was cancelled
+    pub async fn done(&self, id: &str) -> Result<(), Error> {
+        self.append(&[Record::Done { id: id.to_string() }]).await
+    }
+
+    async fn append(&self, records: &[Record]) -> Result<(), Error> {
+        let mut lines = String::new();
+        for record in records {
+            let line = serde_json::to_string(record)
+                .map_err(|e| Error::Handler(format!("Failed to serialize queue record: {}", e)))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        let file = self.file.clone();
+        let write_error = |e: std::io::Error| {
+            Error::Handler(format!(
+                "Failed to write queue file {}: {}",
+                self.path.display(),
+                e
+            ))
+        };
+        tokio::task::spawn_blocking(move || {
+            let mut file = file
+                .lock()
+                .map_err(|_| std::io::Error::other("queue file lock poisoned"))?;
+            file.write_all(lines.as_bytes())
+                .and_then(|_| file.sync_data())
+        })
+        .await
+        .map_err(|e| Error::Handler(format!("Queue file writer failed: {}", e)))?
+        .map_err(write_error)
+    }
+
+    /// Replay the queue file. A line that does not parse, e.g. one cut short by a
+    /// crash, is logged and skipped
+    fn read_pending(path: &Path) -> Result<IndexMap<String, (Job, usize)>> {
+        let mut pending = IndexMap::new();
+        if !path.exists() {
+            return Ok(pending);
+        }
+
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open queue file: {}", path.display()))?;
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(Record::Enqueue { job }) => {
+                    pending.insert(job.id.clone(), (*job, 0));
+                }
+                Ok(Record::Start { id }) => {
+                    if let Some((_, starts)) = pending.get_mut(&id) {
+                        *starts += 1;
+                    }
+                }
+                Ok(Record::Done { id }) => {
+                    pending.shift_remove(&id);
+                }
+                Err(e) => warn!(
+                    "Skipping invalid queue record {}:{}: {}",
+                    path.display(),
+                    number + 1,
+                    e
+                ),
+            }
+        }
+
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::Rule;
+    use tempfile::tempdir;
+    use tera::Context;
+
+    fn create_test_job(id: &str) -> Job {
+        let mut context = Context::new();
+        context.insert("branch", "main");
+        context.insert("env", &serde_json::json!({"SECRET": "secret"}));
+        Job {
+            id: id.to_string(),
+            run_id: "run-1".to_string(),
+            rule_name: "rule".to_string(),
+            rule: Rule::default(),
+            context,
+            not_before: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_store_recovers_pending_jobs() {
+        // Setup
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("queue").join("jobs.jsonl");
+        let (store, pending) = JobStore::open(&path).unwrap();
+        assert!(pending.is_empty());
+
+        store.enqueued(&[create_test_job("a")]).await.unwrap();
+        store
+            .enqueued(&[create_test_job("b"), create_test_job("c")])
+            .await
+            .unwrap();
+        store.done("b").await.unwrap();
+        drop(store);
+
+        // Execute
+        let (_store, pending) = JobStore::open(&path).unwrap();
+
+        // Verify
+        let ids: Vec<_> = pending.iter().map(|(job, _)| job.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        assert_eq!(
+            pending[0].0.context.get("branch"),
+            Some(&serde_json::json!("main"))
+        );
+
+        // the file is compacted to the pending jobs, without environment variables
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert!(!content.contains("secret"));
+        assert!(pending[0].0.context.get("env").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_job_store_skips_invalid_records() {
+        // Setup
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("jobs.jsonl");
+        let (store, _) = JobStore::open(&path).unwrap();
+        store.enqueued(&[create_test_job("a")]).await.unwrap();
+        drop(store);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"op\":\"enq").unwrap();
+
+        // Execute
+        let (_store, pending) = JobStore::open(&path).unwrap();
+
+        // Verify
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].0.id, "a");
+    }
+
+    #[tokio::test]
+    async fn test_job_store_counts_starts() {
+        // Setup
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("jobs.jsonl");
+        let (store, _) = JobStore::open(&path).unwrap();
+        store.enqueued(&[create_test_job("a")]).await.unwrap();
+        store.started("a").await.unwrap();
+        drop(store);
+        let (store, _) = JobStore::open(&path).unwrap();
+        store.started("a").await.unwrap();
+        drop(store);
+
+        // Execute
+        let (_store, pending) = JobStore::open(&path).unwrap();
+
+        // Verify
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].1, 2);
+    }
+}
@@ -1,8 +1,8 @@
 mod app;
 
 use anyhow::Result;
-use app::ServerConfig;
-use clap::Parser;
+use app::{cli::DeadLetterCommand, ServerConfig};
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 /// Git-Actions: A Rust-based automation tool for Git events
@@ -12,6 +12,17 @@ struct Args {
     /// Path to the server configuration file
     #[clap(short, long, default_value = "server.yaml")]
     config: PathBuf,
+
+    /// Command to run instead of the server
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect, replay or discard failed rule runs
+    #[clap(subcommand)]
+    DeadLetters(DeadLetterCommand),
 }
 
 #[tokio::main]
@@ -22,8 +33,11 @@ async fn main() -> Result<()> {
     // Load configuration
     let config = ServerConfig::from_file(&args.config)?;
 
-    // run the server
-    app::run(config).await?;
+    match args.command {
+        Some(Command::DeadLetters(command)) => app::cli::dead_letters(config, command).await?,
+        // run the server
+        None => app::run(config).await?,
+    }
 
     Ok(())
 }
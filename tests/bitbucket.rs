@@ -1,13 +1,13 @@
 use git_actions::app::config::rules::{HttpAction, PathFilter};
 use git_actions::app::config::webhook::{
-    Bitbucket as BitbucketConfig, BitbucketApi, BitbucketAuth,
+    Bitbucket as BitbucketConfig, BitbucketApi, BitbucketAuth, ProjectSpec,
 };
 use git_actions::app::config::{Action, Rule};
 use git_actions::app::webhooks::bitbucket::Bitbucket;
 use git_actions::app::webhooks::types::WebhookTypeHandler;
+use indexmap::IndexMap;
 use serde_json::{json, Value};
-use std::collections::HashMap;
-use wiremock::matchers::{method, path_regex};
+use wiremock::matchers::{header, method, path, path_regex, query_param};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 // Helper functions
@@ -119,8 +119,9 @@ async fn integration_extract_changed_files_success() {
     // Create Bitbucket instance with config and payload
     let bitbucket = Bitbucket {
         config,
-        rules: HashMap::new(),
+        rules: IndexMap::new(),
         payload,
+        projects: vec![],
     };
 
     // Call extract_changed_files and assert result
@@ -187,7 +188,7 @@ async fn integration_rule_evaluation_with_changed_files() {
     };
 
     // Define a rule that matches on changed file "src/main.rs"
-    let mut rules = HashMap::new();
+    let mut rules = IndexMap::new();
 
     let rule = Rule {
         description: Some("main_rs_change".to_string()),
@@ -198,6 +199,7 @@ async fn integration_rule_evaluation_with_changed_files() {
             exact: "src/main.rs".to_string(),
         }]),
         actions: vec![],
+        ..Default::default()
     };
     rules.insert("main_rs_change".to_string(), &rule);
 
@@ -206,6 +208,7 @@ async fn integration_rule_evaluation_with_changed_files() {
         config,
         rules: rules.clone(),
         payload,
+        projects: vec![],
     };
 
     // Extract changed files
@@ -254,8 +257,8 @@ async fn run_webhook_handler_test_case(case: WebhookHandlerTestCase) {
         .mount(&mock_server)
         .await;
 
-    // Prepare rules HashMap for this case
-    let rules_map: HashMap<String, &Rule> = case
+    // Prepare rules map for this case
+    let rules_map: IndexMap<String, &Rule> = case
         .rules_data
         .iter()
         .map(|(name, rule)| (name.to_string(), rule))
@@ -280,6 +283,7 @@ async fn run_webhook_handler_test_case(case: WebhookHandlerTestCase) {
         config,
         rules: rules_map,
         payload: case.payload.clone(), // Clone payload for this iteration
+        projects: vec![],
     };
 
     // Test the run method
@@ -290,7 +294,12 @@ async fn run_webhook_handler_test_case(case: WebhookHandlerTestCase) {
         case.name,
         actions_result.err()
     );
-    let actions = actions_result.unwrap();
+    let (_, evaluated) = actions_result.unwrap();
+    let actions: Vec<&Action> = evaluated
+        .iter()
+        .filter(|evaluated| evaluated.evaluation.matched)
+        .flat_map(|evaluated| evaluated.rule.actions.iter())
+        .collect();
 
     // Verify action count
     assert_eq!(
@@ -345,9 +354,12 @@ async fn test_webhook_handler_rules_match() {
                         url: "https://example.com/webhook".to_string(),
                         headers: None,
                         body: None,
+                        ..Default::default()
                     }),
                     shell: None,
+                    ..Default::default()
                 }],
+                ..Default::default()
             },
         )],
         payload: create_pr_opened_payload(),
@@ -378,9 +390,12 @@ async fn test_webhook_handler_no_rules_match() {
                         url: "https://example.com/webhook".to_string(),
                         headers: None,
                         body: None,
+                        ..Default::default()
                     }),
                     shell: None,
+                    ..Default::default()
                 }],
+                ..Default::default()
             },
         )],
         payload: create_pr_opened_payload(),
@@ -411,9 +426,12 @@ async fn test_webhook_handler_event_type_filtering() {
                         url: "https://example.com/webhook".to_string(),
                         headers: None,
                         body: None,
+                        ..Default::default()
                     }),
                     shell: None,
+                    ..Default::default()
                 }],
+                ..Default::default()
             },
         )],
         payload: create_pr_opened_payload(),
@@ -445,9 +463,12 @@ async fn test_webhook_handler_multiple_rules() {
                             url: "https://example.com/webhook1".to_string(),
                             headers: None,
                             body: None,
+                            ..Default::default()
                         }),
                         shell: None,
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
             ),
             (
@@ -466,9 +487,12 @@ async fn test_webhook_handler_multiple_rules() {
                             url: "https://example.com/webhook2".to_string(),
                             headers: None,
                             body: None,
+                            ..Default::default()
                         }),
                         shell: None,
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
             ),
             (
@@ -487,9 +511,12 @@ async fn test_webhook_handler_multiple_rules() {
                             url: "https://example.com/webhook3".to_string(),
                             headers: None,
                             body: None,
+                            ..Default::default()
                         }),
                         shell: None,
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 },
             ),
         ],
@@ -524,9 +551,12 @@ async fn test_webhook_handler_rule_without_event_type() {
                         url: "https://example.com/any_event".to_string(),
                         headers: None,
                         body: None,
+                        ..Default::default()
                     }),
                     shell: None,
+                    ..Default::default()
                 }],
+                ..Default::default()
             },
         )],
         payload: create_pr_opened_payload(),
@@ -557,9 +587,12 @@ async fn test_webhook_handler_rule_without_branch() {
                         url: "https://example.com/any_branch".to_string(),
                         headers: None,
                         body: None,
+                        ..Default::default()
                     }),
                     shell: None,
+                    ..Default::default()
                 }],
+                ..Default::default()
             },
         )],
         payload: create_pr_opened_payload(),
@@ -588,9 +621,12 @@ async fn test_webhook_handler_rule_without_path() {
                         url: "https://example.com/any_path".to_string(),
                         headers: None,
                         body: None,
+                        ..Default::default()
                     }),
                     shell: None,
+                    ..Default::default()
                 }],
+                ..Default::default()
             },
         )],
         payload: create_pr_opened_payload(),
@@ -599,3 +635,79 @@ async fn test_webhook_handler_rule_without_path() {
     };
     run_webhook_handler_test_case(case).await;
 }
+
+#[tokio::test]
+async fn integration_extract_event_detects_projects() {
+    // Setup
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path_regex(".*/pull-requests/1373/changes$"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(create_bitbucket_api_mock_response(vec![
+                "services/api/src/main.rs",
+                "services/web/package.json",
+                "README.md",
+            ])),
+        )
+        .mount(&mock_server)
+        .await;
+    for marker in ["services/api/Cargo.toml", "services/web/Cargo.toml"] {
+        Mock::given(method("HEAD"))
+            .and(path(format!(
+                "/api/latest/projects/PROJ/repos/REPO/raw/{}",
+                marker
+            )))
+            .and(query_param("at", "cafebabe"))
+            .and(header("Authorization", "Bearer bitbucket-token"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+    }
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_server)
+        .await;
+
+    std::env::set_var("GIT_ACTIONS_IT_BITBUCKET_TOKEN", "bitbucket-token");
+    let mut payload = create_pr_opened_payload();
+    payload["pullRequest"]["fromRef"]["latestCommit"] = json!("cafebabe");
+    let bitbucket = Bitbucket {
+        config: BitbucketConfig {
+            token_from_env: None,
+            api: BitbucketApi {
+                base_url: mock_server.uri(),
+                project: "PROJ".to_string(),
+                repo: "REPO".to_string(),
+                auth: BitbucketAuth {
+                    auth_type: "token".to_string(),
+                    token_from_env: "GIT_ACTIONS_IT_BITBUCKET_TOKEN".to_string(),
+                },
+            },
+        },
+        rules: IndexMap::new(),
+        payload,
+        projects: vec![
+            ProjectSpec::Marker {
+                marker: "Cargo.toml".to_string(),
+            },
+            ProjectSpec::Paths {
+                name: "docs".to_string(),
+                paths: vec!["*.md".to_string()],
+            },
+        ],
+    };
+
+    // Execute
+    let event = bitbucket.extract_event().await.unwrap();
+
+    // Verify
+    let projects: Vec<_> = event
+        .projects
+        .iter()
+        .map(|project| (project.name.as_str(), project.files.len()))
+        .collect();
+    assert_eq!(
+        projects,
+        vec![("services/api", 1), ("services/web", 1), ("docs", 1)]
+    );
+}
@@ -1,5 +1,5 @@
 use git_actions::app::template;
-use git_actions::app::webhooks::types::{Event, EventType};
+use git_actions::app::webhooks::types::{Event, EventType, PullRequest};
 use std::collections::HashMap;
 use std::env;
 use tera::Context;
@@ -11,6 +11,8 @@ fn test_template_rendering_in_http_action() {
         event_type: EventType::Opened,
         branch: "feature/test-branch".to_string(),
         changed_files: vec!["src/main.rs".to_string(), "Cargo.toml".to_string()],
+        pull_request: PullRequest::default(),
+        projects: vec![],
     };
 
     // Set up environment variables